@@ -7,45 +7,187 @@ use crate::day8::Position;
 
 struct Config {
     in_file: String,
+    rule: TrailRule,
+    trail_ends: Option<(Position, Position)>,
+    limit: usize,
+    format: TrailFormat,
 }
 
+enum TrailFormat {
+    List,
+    Map,
+}
+
+const DEFAULT_TRAIL_LIMIT: usize = 10;
+
 impl Config {
     fn new(args: &mut impl Iterator<Item = String>) -> Result<Config, &'static str> {
         let in_file = match args.next() {
             Some(arg) => arg,
             None => return Err("Missing input file argument"),
         };
+        let mut rule = TrailRule::default();
+        let mut trail_ends = None;
+        let mut limit = DEFAULT_TRAIL_LIMIT;
+        let mut format = TrailFormat::List;
+        while let Some(option) = args.next() {
+            let value = args.next().ok_or("Missing option value")?;
+            match option.as_str() {
+                "--start" => rule.start = to_height(&value)?,
+                "--end" => rule.end = to_height(&value)?,
+                "--delta" => {
+                    let (min_delta, max_delta) = value.split_once("..").unwrap_or((&value, &value));
+                    match (min_delta.parse(), max_delta.parse()) {
+                        (Ok(min_delta), Ok(max_delta)) => {
+                            rule.min_delta = min_delta;
+                            rule.max_delta = max_delta;
+                        }
+                        _ => return Err("Delta must be a number or a range like 1..2"),
+                    }
+                }
+                "--trails" => {
+                    let (trailhead, peak) = value
+                        .split_once(':')
+                        .ok_or("Trails must be given as ROW,COL:ROW,COL")?;
+                    trail_ends = Some((to_position(trailhead)?, to_position(peak)?));
+                }
+                "--limit" => limit = value.parse().map_err(|_| "Limit must be a number")?,
+                "--format" => {
+                    format = match value.as_str() {
+                        "list" => TrailFormat::List,
+                        "map" => TrailFormat::Map,
+                        _ => return Err("Format must be list or map"),
+                    }
+                }
+                _ => {
+                    return Err(
+                        "Unknown option, expected --start, --end, --delta, --trails, --limit or --format",
+                    )
+                }
+            }
+        }
+        rule.validate()?;
+
+        Ok(Config {
+            in_file,
+            rule,
+            trail_ends,
+            limit,
+            format,
+        })
+    }
+}
+
+fn to_height(raw_height: &str) -> Result<u8, &'static str> {
+    match raw_height.parse() {
+        Ok(height) if height <= 9 => Ok(height),
+        _ => Err("Height must be a digit between 0 and 9"),
+    }
+}
+
+fn to_position(raw_position: &str) -> Result<Position, &'static str> {
+    let error = "Position must be given as ROW,COL";
+    let (row, col) = raw_position.split_once(',').ok_or(error)?;
+    match (row.parse(), col.parse()) {
+        (Ok(row), Ok(col)) => Ok(Position { row, col }),
+        _ => Err(error),
+    }
+}
+
+// a trail goes from `start` to `end` height, every step changing the height by
+// `min_delta..=max_delta`
+struct TrailRule {
+    start: u8,
+    end: u8,
+    min_delta: i8,
+    max_delta: i8,
+}
 
-        Ok(Config { in_file })
+impl Default for TrailRule {
+    fn default() -> Self {
+        TrailRule {
+            start: 0,
+            end: 9,
+            min_delta: 1,
+            max_delta: 1,
+        }
     }
 }
 
-#[derive(Clone)]
-enum Direction {
-    Up,
-    Down,
+impl TrailRule {
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.min_delta > self.max_delta {
+            return Err("Delta range must be ascending");
+        }
+        // heights are single digits, so this also keeps the reversed range within `i8`
+        if self.min_delta < -9 || self.max_delta > 9 {
+            return Err("Delta must be between -9 and 9");
+        }
+        match self.start.cmp(&self.end) {
+            std::cmp::Ordering::Equal => Err("Start and end heights must differ"),
+            std::cmp::Ordering::Less if self.max_delta <= 0 => {
+                Err("Delta must allow moving from the start height towards the end height")
+            }
+            std::cmp::Ordering::Greater if self.min_delta >= 0 => {
+                Err("Delta must allow moving from the start height towards the end height")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // every step gets closer to the end height, so trails can never go in circles
+    fn is_monotonic(&self) -> bool {
+        match self.start.cmp(&self.end) {
+            std::cmp::Ordering::Less => self.min_delta > 0,
+            _ => self.max_delta < 0,
+        }
+    }
+
+    fn reverse(&self) -> TrailRule {
+        TrailRule {
+            start: self.end,
+            end: self.start,
+            min_delta: -self.max_delta,
+            max_delta: -self.min_delta,
+        }
+    }
+
+    fn allows(&self, from_height: u8, to_height: u8) -> bool {
+        let delta = to_height as i8 - from_height as i8;
+        let lowest = self.start.min(self.end);
+        let highest = self.start.max(self.end);
+        (self.min_delta..=self.max_delta).contains(&delta)
+            && (lowest..=highest).contains(&to_height)
+    }
 }
 
-struct TrailPossibleFromSource {
-    source: HashMap<Position, u8>,
-    direction: Direction,
-    current_height: u8,
-    current_position: Position,
+// `None` marks an impassable cell
+type Maps = Vec<Vec<Option<u8>>>;
+
+fn get_height(maps: &Maps, position: &Position) -> Option<u8> {
+    maps.get(position.row as usize)
+        .and_then(|row| row.get(position.col as usize))
+        .copied()
+        .flatten()
 }
 
-fn can_step_to(maps: &Vec<Vec<u8>>, position: &Position, to_height: &u8) -> HashSet<Position> {
+fn can_step_to(maps: &Maps, position: &Position, rule: &TrailRule) -> HashSet<Position> {
+    let from_height = match get_height(maps, position) {
+        Some(height) => height,
+        None => return HashSet::new(),
+    };
     [-1, 1]
         .into_iter()
         .flat_map(|offset| {
             let mut expand: Vec<Position> = Vec::new();
-            let row = position.row as i8 + offset;
+            let row = position.row as i16 + offset;
             if row >= 0 {
                 expand.push(Position {
                     row: row as u8,
                     col: position.col,
                 });
             };
-            let col = position.col as i8 + offset;
+            let col = position.col as i16 + offset;
             if col >= 0 {
                 expand.push(Position {
                     row: position.row,
@@ -54,74 +196,12 @@ fn can_step_to(maps: &Vec<Vec<u8>>, position: &Position, to_height: &u8) -> Hash
             };
             expand
         })
-        .filter_map(|position| {
-            maps.get(position.row as usize)
-                .and_then(|row| row.get(position.col as usize))
-                .and_then(|height| {
-                    if height == to_height {
-                        Some(position)
-                    } else {
-                        None
-                    }
-                })
+        .filter(|position| {
+            get_height(maps, position).is_some_and(|height| rule.allows(from_height, height))
         })
         .collect()
 }
 
-fn step(
-    maps: &Vec<Vec<u8>>,
-    trail_path: &TrailPossibleFromSource,
-) -> HashMap<Position, TrailPossibleFromSource> {
-    let next_height = match trail_path.direction {
-        Direction::Up => trail_path.current_height + 1,
-        Direction::Down => trail_path.current_height - 1,
-    };
-    can_step_to(maps, &trail_path.current_position, &next_height)
-        .into_iter()
-        .fold(HashMap::new(), |mut acc, position| {
-            acc.insert(
-                position,
-                TrailPossibleFromSource {
-                    source: trail_path.source.clone(),
-                    direction: trail_path.direction.clone(),
-                    current_height: next_height,
-                    current_position: position,
-                },
-            );
-            acc
-        })
-}
-
-fn steps(
-    maps: &Vec<Vec<u8>>,
-    trail_paths: &HashMap<Position, TrailPossibleFromSource>,
-) -> HashMap<Position, TrailPossibleFromSource> {
-    trail_paths
-        .into_iter()
-        .fold(HashMap::new(), |mut acc, (_, trail_path)| {
-            step(maps, trail_path)
-                .into_iter()
-                .for_each(|(position, trail_path)| {
-                    acc.entry(position)
-                        .and_modify(|exist_trail_path| {
-                            trail_path.source.iter().for_each(
-                                |(source_position_in_new_step, path_count)| {
-                                    exist_trail_path
-                                        .source
-                                        .entry(*source_position_in_new_step)
-                                        .and_modify(|exist_path_count| {
-                                            *exist_path_count += path_count;
-                                        })
-                                        .or_insert(*path_count);
-                                },
-                            )
-                        })
-                        .or_insert(trail_path);
-                });
-            acc
-        })
-}
-
 fn read_input_file(file_path: &str) -> String {
     fs::read_to_string(file_path).unwrap_or_else(|err| {
         eprintln!("Problem reading file: {}", err);
@@ -129,7 +209,7 @@ fn read_input_file(file_path: &str) -> String {
     })
 }
 
-fn get_positions_by_height(maps: &Vec<Vec<u8>>, target_height: u8) -> HashSet<Position> {
+fn get_positions_by_height(maps: &Maps, target_height: u8) -> HashSet<Position> {
     maps.iter()
         .enumerate()
         .fold(HashSet::new(), |mut acc, (row_i, col)| {
@@ -137,7 +217,7 @@ fn get_positions_by_height(maps: &Vec<Vec<u8>>, target_height: u8) -> HashSet<Po
                 col.iter()
                     .enumerate()
                     .filter_map(|(col_i, &height)| match height {
-                        height if target_height == height => Some(Position {
+                        Some(height) if target_height == height => Some(Position {
                             row: row_i as u8,
                             col: col_i as u8,
                         }),
@@ -148,110 +228,219 @@ fn get_positions_by_height(maps: &Vec<Vec<u8>>, target_height: u8) -> HashSet<Po
         })
 }
 
-fn process(raw_dataset: &str) -> HashMap<Position, HashMap<Position, u8>> {
-    let maps: Vec<Vec<u8>> = raw_dataset
+fn to_maps(raw_dataset: &str) -> Maps {
+    raw_dataset
         .lines()
         .map(|line| {
             line.chars()
-                .map(|c| c.to_digit(10).unwrap() as u8)
+                .map(|c| c.to_digit(10).map(|height| height as u8))
                 .collect()
         })
-        .collect();
-    let trailheads = get_positions_by_height(&maps, 0);
-    let trail_peaks = get_positions_by_height(&maps, 9);
-    let mut head_paths: HashMap<Position, TrailPossibleFromSource> = trailheads
+        .collect()
+}
+
+fn walk_simple_paths(
+    maps: &Maps,
+    rule: &TrailRule,
+    position: Position,
+    visited: &mut HashSet<Position>,
+    peaks: &mut HashMap<Position, u64>,
+) {
+    if visited.len() > 1 && get_height(maps, &position) == Some(rule.end) {
+        *peaks.entry(position).or_insert(0) += 1;
+        return;
+    }
+    for next_position in can_step_to(maps, &position, rule) {
+        if visited.insert(next_position) {
+            walk_simple_paths(maps, rule, next_position, visited, peaks);
+            visited.remove(&next_position);
+        }
+    }
+}
+
+// with a delta range going both up and down, a trail is any path that never visits a cell
+// twice, every one of them is walked so this only scales to small maps
+fn count_simple_paths(maps: &Maps, rule: &TrailRule) -> HashMap<Position, HashMap<Position, u64>> {
+    get_positions_by_height(maps, rule.start)
         .into_iter()
-        .map(|position| {
-            let mut source = HashMap::new();
-            source.insert(position, 1);
-            (
-                position,
-                TrailPossibleFromSource {
-                    source,
-                    direction: Direction::Up,
-                    current_height: 0,
-                    current_position: position,
-                },
-            )
+        .filter_map(|trailhead| {
+            let mut peaks = HashMap::new();
+            let mut visited = HashSet::from([trailhead]);
+            walk_simple_paths(maps, rule, trailhead, &mut visited, &mut peaks);
+            (!peaks.is_empty()).then_some((trailhead, peaks))
         })
-        .collect();
-    let mut peak_paths: HashMap<Position, TrailPossibleFromSource> = trail_peaks
-        .into_iter()
-        .map(|position| {
-            let mut source = HashMap::new();
-            source.insert(position, 1);
-            (
-                position,
-                TrailPossibleFromSource {
-                    source,
-                    direction: Direction::Down,
-                    current_height: 9,
-                    current_position: position,
-                },
-            )
+        .collect()
+}
+
+fn process(raw_dataset: &str, rule: &TrailRule) -> HashMap<Position, HashMap<Position, u64>> {
+    let maps = to_maps(raw_dataset);
+    if !rule.is_monotonic() {
+        return count_simple_paths(&maps, rule);
+    }
+    // every step moves strictly away from the start height, so visiting the cells by their
+    // height distance from the start handles each cell after all of its predecessors
+    let mut positions: Vec<(u8, Position)> = maps
+        .iter()
+        .enumerate()
+        .flat_map(|(row_i, row)| {
+            row.iter().enumerate().filter_map(move |(col_i, height)| {
+                height.map(|height| {
+                    (
+                        height.abs_diff(rule.start),
+                        Position {
+                            row: row_i as u8,
+                            col: col_i as u8,
+                        },
+                    )
+                })
+            })
         })
         .collect();
-    for _ in 0..4 {
-        head_paths = steps(&maps, &head_paths);
-        peak_paths = steps(&maps, &peak_paths);
+    positions.sort_by_key(|(distance, _)| *distance);
+
+    let mut sources: HashMap<Position, HashMap<Position, u64>> =
+        get_positions_by_height(&maps, rule.start)
+            .into_iter()
+            .map(|position| (position, HashMap::from([(position, 1)])))
+            .collect();
+    for (_, position) in positions {
+        if get_height(&maps, &position) == Some(rule.end) {
+            continue;
+        }
+        let trailheads = match sources.get(&position) {
+            Some(trailheads) => trailheads.clone(),
+            None => continue,
+        };
+        for next_position in can_step_to(&maps, &position, rule) {
+            let next_trailheads = sources.entry(next_position).or_default();
+            for (trailhead, path_count) in trailheads.iter() {
+                *next_trailheads.entry(*trailhead).or_insert(0) += path_count;
+            }
+        }
     }
-    head_paths = steps(&maps, &head_paths);
 
-    let relations: HashMap<Position, HashMap<Position, u8>> = head_paths
-        .into_iter()
-        .filter(|(position, _)| peak_paths.contains_key(position))
-        .fold(HashMap::new(), |mut acc, (position, headtrail_path)| {
-            let peak_positions = peak_paths
-                .get(&position)
-                .and_then(|peak_trail_path| Some(&peak_trail_path.source))
-                // we already filter out the head_paths that are not in peak_paths
-                .unwrap();
-            headtrail_path.source.into_iter().for_each(
-                |(headtrail_position, path_count_from_head)| {
-                    acc.entry(headtrail_position)
-                        .and_modify(|exist_peak_positions| {
-                            peak_positions.iter().for_each(
-                                |(peak_position, path_to_peak_count)| {
-                                    exist_peak_positions
-                                        .entry(*peak_position)
-                                        .and_modify(|exist_path_count| {
-                                            *exist_path_count +=
-                                                path_to_peak_count * path_count_from_head;
-                                        })
-                                        .or_insert(*path_to_peak_count * path_count_from_head);
-                                },
-                            );
-                        })
-                        .or_insert({
-                            let mut peak_with_score = HashMap::new();
-                            peak_positions.iter().for_each(
-                                |(peak_position, path_to_peak_count)| {
-                                    peak_with_score.insert(
-                                        *peak_position,
-                                        path_to_peak_count * path_count_from_head,
-                                    );
-                                },
-                            );
-                            peak_with_score
-                        });
-                },
-            );
-            acc
-        });
+    let mut relations: HashMap<Position, HashMap<Position, u64>> = HashMap::new();
+    for peak in get_positions_by_height(&maps, rule.end) {
+        for (trailhead, path_count) in sources.remove(&peak).unwrap_or_default() {
+            relations
+                .entry(trailhead)
+                .or_default()
+                .insert(peak, path_count);
+        }
+    }
     relations
 }
 
-fn process_first(relations: &HashMap<Position, HashMap<Position, u8>>) -> usize {
+// depth first walk over the trails, only stepping on cells that can still reach the peak
+struct Trails<'a> {
+    maps: &'a Maps,
+    rule: &'a TrailRule,
+    peak: Position,
+    reach_peak: HashSet<Position>,
+    stack: Vec<Vec<Position>>,
+}
+
+impl<'a> Trails<'a> {
+    fn new(maps: &'a Maps, rule: &'a TrailRule, trailhead: Position, peak: Position) -> Self {
+        let mut reach_peak = HashSet::new();
+        if get_height(maps, &trailhead) == Some(rule.start)
+            && get_height(maps, &peak) == Some(rule.end)
+        {
+            reach_peak.insert(peak);
+            let mut frontier = vec![peak];
+            while let Some(position) = frontier.pop() {
+                for previous in can_step_to(maps, &position, &rule.reverse()) {
+                    if reach_peak.insert(previous) {
+                        frontier.push(previous);
+                    }
+                }
+            }
+        }
+        let stack = match reach_peak.contains(&trailhead) {
+            true => vec![vec![trailhead]],
+            false => Vec::new(),
+        };
+        Trails {
+            maps,
+            rule,
+            peak,
+            reach_peak,
+            stack,
+        }
+    }
+}
+
+impl Iterator for Trails<'_> {
+    type Item = Vec<Position>;
+
+    fn next(&mut self) -> Option<Vec<Position>> {
+        while let Some(trail) = self.stack.pop() {
+            let last = *trail.last().unwrap();
+            if last == self.peak {
+                return Some(trail);
+            }
+            // trails end on the first peak they reach and never visit a cell twice, which only
+            // matters when the delta range goes both up and down
+            if trail.len() > 1 && get_height(self.maps, &last) == Some(self.rule.end) {
+                continue;
+            }
+            for next_position in can_step_to(self.maps, &last, self.rule) {
+                if self.reach_peak.contains(&next_position) && !trail.contains(&next_position) {
+                    let mut next_trail = trail.clone();
+                    next_trail.push(next_position);
+                    self.stack.push(next_trail);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn format_trail(trail: &[Position]) -> String {
+    trail
+        .iter()
+        .map(|position| format!("({},{})", position.row, position.col))
+        .collect::<Vec<String>>()
+        .join(" -> ")
+}
+
+// the map with only the heights on the trail, other cells shown as `.`
+fn render_trail(maps: &Maps, trail: &[Position]) -> String {
+    let on_trail: HashSet<&Position> = trail.iter().collect();
+    maps.iter()
+        .enumerate()
+        .map(|(row_i, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(col_i, height)| {
+                    let position = Position {
+                        row: row_i as u8,
+                        col: col_i as u8,
+                    };
+                    match height {
+                        Some(height) if on_trail.contains(&position) => {
+                            char::from_digit(*height as u32, 10).unwrap()
+                        }
+                        _ => '.',
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn process_first(relations: &HashMap<Position, HashMap<Position, u64>>) -> usize {
     relations
         .iter()
         .map(|(_, peak_positions)| peak_positions.len())
         .sum()
 }
 
-fn process_second(relations: &HashMap<Position, HashMap<Position, u8>>) -> usize {
+fn process_second(relations: &HashMap<Position, HashMap<Position, u64>>) -> usize {
     relations
         .iter()
-        .map(|(_, peak_positions)| peak_positions.values().sum::<u8>() as usize)
+        .map(|(_, peak_positions)| peak_positions.values().sum::<u64>() as usize)
         .sum()
 }
 
@@ -264,13 +453,26 @@ pub fn run(mut args: impl Iterator<Item = String>) {
 
     let raw_dataset = read_input_file(&config.in_file);
 
-    let relations = process(&raw_dataset);
+    let relations = process(&raw_dataset, &config.rule);
 
     let scores_sum = process_first(&relations);
     println!("Sum of the scores of all trailheads: {}", scores_sum);
-    
+
     let total_paths = process_second(&relations);
     println!("Total paths from trailheads to trailpeaks: {}", total_paths);
+
+    if let Some((trailhead, peak)) = config.trail_ends {
+        let maps = to_maps(&raw_dataset);
+        let mut trail_count = 0;
+        for trail in Trails::new(&maps, &config.rule, trailhead, peak).take(config.limit) {
+            trail_count += 1;
+            match config.format {
+                TrailFormat::List => println!("{}", format_trail(&trail)),
+                TrailFormat::Map => println!("{}\n", render_trail(&maps, &trail)),
+            }
+        }
+        println!("Trails shown: {}", trail_count);
+    }
 }
 
 #[cfg(test)]
@@ -280,15 +482,154 @@ mod tests {
     #[test]
     fn test_process_ex() {
         let raw_dataset = read_input_file("input/day10_ex.txt");
-        let relations = process(&raw_dataset);
+        let relations = process(&raw_dataset, &TrailRule::default());
+        assert_eq!(process_first(&relations), 36);
+        assert_eq!(process_second(&relations), 81);
+    }
+
+    #[test]
+    fn test_process_rule() {
+        let raw_dataset = read_input_file("input/day10_ex.txt");
+        let descent = TrailRule {
+            start: 9,
+            end: 0,
+            min_delta: -1,
+            max_delta: -1,
+        };
+        let relations = process(&raw_dataset, &descent);
         assert_eq!(process_first(&relations), 36);
         assert_eq!(process_second(&relations), 81);
+
+        let raw_dataset = "013\n.23";
+        let climb = TrailRule {
+            start: 0,
+            end: 3,
+            min_delta: 1,
+            max_delta: 2,
+        };
+        let relations = process(raw_dataset, &climb);
+        assert_eq!(process_first(&relations), 2);
+        assert_eq!(process_second(&relations), 2);
+        let relations = process(
+            raw_dataset,
+            &TrailRule {
+                end: 3,
+                ..TrailRule::default()
+            },
+        );
+        assert_eq!(process_first(&relations), 1);
+
+        // trails may drop back down as long as they never visit a cell twice
+        let mixed = TrailRule {
+            start: 0,
+            end: 3,
+            min_delta: -1,
+            max_delta: 1,
+        };
+        assert!(mixed.validate().is_ok());
+        assert!(!mixed.is_monotonic());
+        let relations = process("012\n123", &mixed);
+        assert_eq!(process_first(&relations), 1);
+        assert_eq!(process_second(&relations), 4);
+        let relations = process("0101\n...2", &TrailRule { end: 2, ..mixed });
+        assert_eq!(process_first(&relations), 2);
+        let relations = process(
+            "0101\n...2",
+            &TrailRule {
+                end: 2,
+                ..TrailRule::default()
+            },
+        );
+        assert_eq!(process_first(&relations), 1);
+
+        let backwards = TrailRule {
+            max_delta: 0,
+            ..mixed
+        };
+        assert!(backwards.validate().is_err());
+        let too_steep = TrailRule {
+            start: 9,
+            end: 0,
+            min_delta: i8::MIN,
+            max_delta: -1,
+        };
+        assert!(too_steep.validate().is_err());
+        assert!(climb.validate().is_ok());
+        assert!(descent.validate().is_ok());
+    }
+
+    #[test]
+    fn test_trails() {
+        let raw_dataset = read_input_file("input/day10_ex.txt");
+        let rule = TrailRule::default();
+        let maps = to_maps(&raw_dataset);
+        let relations = process(&raw_dataset, &rule);
+        for (trailhead, peaks) in relations.iter() {
+            for (peak, path_count) in peaks.iter() {
+                let trails: Vec<Vec<Position>> =
+                    Trails::new(&maps, &rule, *trailhead, *peak).collect();
+                assert_eq!(trails.len() as u64, *path_count);
+                assert!(trails.iter().all(|trail| trail.len() == 10));
+            }
+        }
+        let trailhead = to_position("0,2").unwrap();
+        let peak = to_position("3,4").unwrap();
+        assert_eq!(
+            Trails::new(&maps, &rule, trailhead, peak).take(3).count(),
+            3
+        );
+        assert_eq!(Trails::new(&maps, &rule, trailhead, trailhead).count(), 0);
+
+        let raw_dataset = "0123\n.874\n.965";
+        let maps = to_maps(raw_dataset);
+        let peak = to_position("2,1").unwrap();
+        let trail = Trails::new(&maps, &rule, to_position("0,0").unwrap(), peak)
+            .next()
+            .unwrap();
+        assert_eq!(
+            format_trail(&trail),
+            "(0,0) -> (0,1) -> (0,2) -> (0,3) -> (1,3) -> (2,3) -> (2,2) -> (1,2) -> (1,1) -> (2,1)"
+        );
+        assert_eq!(render_trail(&maps, &trail[..4]), "0123\n....\n....");
+
+        // the count from one trailhead goes beyond u8 only when summed over its peaks
+        let raw_dataset = (0..10)
+            .map(|row| {
+                (0..10)
+                    .map(|col| match row + col {
+                        height if height <= 9 => char::from_digit(height, 10).unwrap(),
+                        _ => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        let relations = process(&raw_dataset, &rule);
+        assert_eq!(process_first(&relations), 10);
+        assert_eq!(process_second(&relations), 512);
+
+        // a flat 5x5 plateau braids 8512 trails between a single trailhead and peak
+        let raw_dataset = "01111\n11111\n11111\n11111\n1111123456789";
+        let maps = to_maps(raw_dataset);
+        let plateau = TrailRule {
+            min_delta: 0,
+            ..TrailRule::default()
+        };
+        let trailhead = to_position("0,0").unwrap();
+        let peak = to_position("4,12").unwrap();
+        let relations = process(raw_dataset, &plateau);
+        assert_eq!(relations[&trailhead][&peak], 8512);
+        let trails: Vec<Vec<Position>> = Trails::new(&maps, &plateau, trailhead, peak).collect();
+        assert_eq!(trails.len(), 8512);
+        assert!(trails
+            .iter()
+            .all(|trail| { trail.iter().collect::<HashSet<&Position>>().len() == trail.len() }));
     }
 
     #[test]
     fn test_process() {
         let raw_dataset = read_input_file("input/day10.txt");
-        let relations = process(&raw_dataset);
+        let relations = process(&raw_dataset, &TrailRule::default());
         assert_eq!(process_first(&relations), 489);
         assert_eq!(process_second(&relations), 1086);
     }
@@ -1,17 +1,54 @@
-use std::{collections::HashMap, fs};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
 
 struct Config {
     in_file: String,
+    rules_file: Option<String>,
+    blink_counts: Vec<usize>,
+    analytics_file: Option<String>,
+    top: usize,
 }
 
+const DEFAULT_TOP: usize = 3;
+
 impl Config {
     fn new(args: &mut impl Iterator<Item = String>) -> Result<Config, &'static str> {
         let in_file = match args.next() {
             Some(arg) => arg,
             None => return Err("Missing input file argument"),
         };
+        let mut rules_file = None;
+        let mut blink_counts = vec![25, 75];
+        let mut analytics_file = None;
+        let mut top = DEFAULT_TOP;
+        while let Some(option) = args.next() {
+            let value = args.next().ok_or("Missing option value")?;
+            match option.as_str() {
+                "--rules" => rules_file = Some(value),
+                "--blinks" => {
+                    blink_counts = value
+                        .split(',')
+                        .map(|raw_count| raw_count.parse())
+                        .collect::<Result<Vec<usize>, _>>()
+                        .map_err(|_| "Blinks must be a list of numbers like 25,75")?
+                }
+                "--analytics" => analytics_file = Some(value),
+                "--top" => top = value.parse().map_err(|_| "Top must be a number")?,
+                _ => {
+                    return Err("Unknown option, expected --rules, --blinks, --analytics or --top")
+                }
+            }
+        }
 
-        Ok(Config { in_file })
+        Ok(Config {
+            in_file,
+            rules_file,
+            blink_counts,
+            analytics_file,
+            top,
+        })
     }
 }
 
@@ -22,42 +59,265 @@ fn read_input_file(file_path: &str) -> String {
     })
 }
 
-fn blink(stone: usize) -> Vec<usize> {
-    if stone == 0 {
-        return vec![1];
+type Predicate = Box<dyn Fn(u64) -> bool>;
+// None when a new stone value does not fit in a u64
+type Replacement = Box<dyn Fn(u64) -> Option<Vec<u64>>>;
+
+struct Rule {
+    predicate: Predicate,
+    replacement: Replacement,
+}
+
+impl Rule {
+    fn new(
+        predicate: impl Fn(u64) -> bool + 'static,
+        replacement: impl Fn(u64) -> Option<Vec<u64>> + 'static,
+    ) -> Rule {
+        Rule {
+            predicate: Box::new(predicate),
+            replacement: Box::new(replacement),
+        }
+    }
+}
+
+fn count_digits(stone: u64) -> u32 {
+    stone.checked_ilog10().unwrap_or(0) + 1
+}
+
+fn split_digits(stone: u64) -> Option<Vec<u64>> {
+    let half = 10u64.pow(count_digits(stone) / 2);
+    Some(vec![stone / half, stone % half])
+}
+
+fn to_number(raw_number: &str) -> Result<u64, &'static str> {
+    raw_number
+        .trim()
+        .parse()
+        .map_err(|_| "Rule numbers must be non negative integers")
+}
+
+fn to_predicate(raw_condition: &str) -> Result<Predicate, &'static str> {
+    let predicate: Predicate = match raw_condition.split_whitespace().collect::<Vec<&str>>()[..] {
+        ["*"] => Box::new(|_| true),
+        ["even_digits"] => Box::new(|stone| count_digits(stone).is_multiple_of(2)),
+        ["odd_digits"] => Box::new(|stone| count_digits(stone) % 2 == 1),
+        ["div", divisor] => match to_number(divisor)? {
+            0 => return Err("Rule divisor must not be 0"),
+            divisor => Box::new(move |stone| stone % divisor == 0),
+        },
+        [value] => {
+            let value = to_number(value)?;
+            Box::new(move |stone| stone == value)
+        }
+        _ => return Err("Unknown rule condition"),
+    };
+    Ok(predicate)
+}
+
+fn to_replacement(raw_replacement: &str) -> Result<Replacement, &'static str> {
+    let parts = raw_replacement
+        .split(',')
+        .map(|raw_part| {
+            let part: Replacement = match raw_part.split_whitespace().collect::<Vec<&str>>()[..] {
+                ["x"] => Box::new(|stone| Some(vec![stone])),
+                ["split"] => Box::new(split_digits),
+                ["mul", factor] => {
+                    let factor = to_number(factor)?;
+                    Box::new(move |stone| Some(vec![stone.checked_mul(factor)?]))
+                }
+                ["add", addend] => {
+                    let addend = to_number(addend)?;
+                    Box::new(move |stone| Some(vec![stone.checked_add(addend)?]))
+                }
+                [value] => {
+                    let value = to_number(value)?;
+                    Box::new(move |_| Some(vec![value]))
+                }
+                _ => return Err("Unknown rule replacement"),
+            };
+            Ok(part)
+        })
+        .collect::<Result<Vec<Replacement>, &'static str>>()?;
+    Ok(Box::new(move |stone| {
+        parts.iter().try_fold(Vec::new(), |mut acc, part| {
+            acc.extend(part(stone)?);
+            Some(acc)
+        })
+    }))
+}
+
+struct Rules(Vec<Rule>);
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules(vec![
+            Rule::new(|stone| stone == 0, |_| Some(vec![1])),
+            Rule::new(|stone| count_digits(stone).is_multiple_of(2), split_digits),
+            Rule::new(|_| true, |stone| Some(vec![stone.checked_mul(2024)?])),
+        ])
+    }
+}
+
+impl Rules {
+    // one rule per line as `condition -> replacement`, the first matching rule is applied
+    // and a stone matching no rule stays as it is
+    //   condition: `N`, `even_digits`, `odd_digits`, `div N` or `*`
+    //   replacement: comma separated `N`, `x` (the stone), `mul N`, `add N` or `split`
+    fn from_str(raw_rules: &str) -> Result<Rules, &'static str> {
+        raw_rules
+            .lines()
+            .map(|line| line.split('#').next().unwrap().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (condition, replacement) = line
+                    .split_once("->")
+                    .ok_or("Rule must be written as `condition -> replacement`")?;
+                Ok(Rule {
+                    predicate: to_predicate(condition)?,
+                    replacement: to_replacement(replacement)?,
+                })
+            })
+            .collect::<Result<Vec<Rule>, &'static str>>()
+            .map(Rules)
+    }
+
+    fn blink(&self, stone: u64) -> Result<Vec<u64>, &'static str> {
+        match self.0.iter().find(|rule| (rule.predicate)(stone)) {
+            Some(rule) => (rule.replacement)(stone).ok_or("Stone value does not fit in a u64"),
+            None => Ok(vec![stone]),
+        }
     }
-    let num_str = stone.to_string();
-    if num_str.len() % 2 == 0 {
-        let half = num_str.len() / 2;
-        let (front, back) = num_str.split_at(half);
-        return vec![front.parse().unwrap(), back.parse().unwrap()];
+}
+
+// stone value to the number of stones carrying it
+type Histogram = HashMap<u64, u128>;
+
+fn to_histogram(stones: &[u64]) -> Histogram {
+    stones.iter().fold(HashMap::new(), |mut acc, stone| {
+        *acc.entry(*stone).or_insert(0) += 1;
+        acc
+    })
+}
+
+// `dict` caches the stones each value turns into and is only valid for the rules it was filled with
+fn blink_histogram(
+    rules: &Rules,
+    histogram: &Histogram,
+    dict: &mut HashMap<u64, Vec<u64>>,
+) -> Result<Histogram, &'static str> {
+    let mut next_histogram = HashMap::new();
+    for (stone, count) in histogram.iter() {
+        if !dict.contains_key(stone) {
+            dict.insert(*stone, rules.blink(*stone)?);
+        }
+        for next_stone in dict[stone].iter() {
+            let next_count = next_histogram.entry(*next_stone).or_insert(0u128);
+            *next_count = next_count
+                .checked_add(*count)
+                .ok_or("Stone count does not fit in a u128")?;
+        }
     }
-    return vec![stone * 2024];
+    Ok(next_histogram)
+}
+
+fn count_stones(histogram: &Histogram) -> Result<u128, &'static str> {
+    histogram
+        .values()
+        .try_fold(0u128, |acc, count| acc.checked_add(*count))
+        .ok_or("Stone count does not fit in a u128")
 }
 
-fn blink_dfs(stone: &usize, level: u8, dict: &mut HashMap<(usize, u8), usize>) -> usize {
-    if level == 0 {
-        1
-    } else if let Some(&result) = dict.get(&(*stone, level)) {
-        result
-    } else {
-        let result = blink(*stone)
-            .into_iter()
-            .map(|next_stone| blink_dfs(&next_stone, level - 1, dict))
-            .sum();
-        dict.insert((*stone, level), result);
-        result
+fn process(
+    rules: &Rules,
+    stones: &[u64],
+    dict: &mut HashMap<u64, Vec<u64>>,
+    blink_count: usize,
+) -> Result<u128, &'static str> {
+    let mut histogram = to_histogram(stones);
+    for _ in 0..blink_count {
+        histogram = blink_histogram(rules, &histogram, dict)?;
     }
+    count_stones(&histogram)
 }
 
-fn process(stones: &Vec<usize>, mut dict: &mut HashMap<(usize, u8), usize>, blink_count: u8) -> usize {
-    stones
-        .into_iter()
-        .map(|stone| blink_dfs(&stone, blink_count, &mut dict))
-        .sum()
+struct BlinkStats {
+    blink: usize,
+    stones: u128,
+    distinct: usize,
+    new_values: usize,
+    seen_values: usize,
+    top: Vec<(u64, u128)>,
 }
 
-fn to_stones(raw_dataset: &str) -> Vec<usize> {
+fn get_blink_stats(
+    blink: usize,
+    histogram: &Histogram,
+    seen: &mut HashSet<u64>,
+    top: usize,
+) -> Result<BlinkStats, &'static str> {
+    let new_values = histogram
+        .keys()
+        .filter(|stone| seen.insert(**stone))
+        .count();
+    let mut frequent: Vec<(u64, u128)> = histogram
+        .iter()
+        .map(|(stone, count)| (*stone, *count))
+        .collect();
+    frequent.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    frequent.truncate(top);
+    Ok(BlinkStats {
+        blink,
+        stones: count_stones(histogram)?,
+        distinct: histogram.len(),
+        new_values,
+        seen_values: seen.len(),
+        top: frequent,
+    })
+}
+
+// stats for the initial stones and after every blink up to `blink_count`
+fn analyse(
+    rules: &Rules,
+    stones: &[u64],
+    dict: &mut HashMap<u64, Vec<u64>>,
+    blink_count: usize,
+    top: usize,
+) -> Result<Vec<BlinkStats>, &'static str> {
+    let mut histogram = to_histogram(stones);
+    let mut seen = HashSet::new();
+    let mut stats = vec![get_blink_stats(0, &histogram, &mut seen, top)?];
+    for blink in 1..=blink_count {
+        histogram = blink_histogram(rules, &histogram, dict)?;
+        stats.push(get_blink_stats(blink, &histogram, &mut seen, top)?);
+    }
+    Ok(stats)
+}
+
+// the first blink from which no new stone value showed up, None if values still appear at the
+// last analysed blink
+fn get_saturation_blink(stats: &[BlinkStats]) -> Option<usize> {
+    let last_growth = stats.iter().rposition(|stat| stat.new_values > 0)?;
+    stats.get(last_growth + 1).map(|stat| stat.blink)
+}
+
+fn to_csv(stats: &[BlinkStats]) -> String {
+    let mut csv = String::from("blink,stones,distinct,new_values,seen_values,most_frequent\n");
+    for stat in stats {
+        let top = stat
+            .top
+            .iter()
+            .map(|(stone, count)| format!("{}:{}", stone, count))
+            .collect::<Vec<String>>()
+            .join(" ");
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            stat.blink, stat.stones, stat.distinct, stat.new_values, stat.seen_values, top
+        ));
+    }
+    csv
+}
+
+fn to_stones(raw_dataset: &str) -> Vec<u64> {
     raw_dataset
         .split_whitespace()
         .map(|num_str| num_str.parse().unwrap())
@@ -73,14 +333,41 @@ pub fn run(mut args: impl Iterator<Item = String>) {
 
     let raw_dataset = read_input_file(&config.in_file);
 
+    let rules = match config.rules_file {
+        Some(rules_file) => Rules::from_str(&read_input_file(&rules_file)).unwrap_or_else(|err| {
+            eprintln!("Problem parsing rules: {}", err);
+            std::process::exit(1);
+        }),
+        None => Rules::default(),
+    };
+
     let stones = to_stones(&raw_dataset);
-    let mut dict: HashMap<(usize, u8), usize> = HashMap::new();
+    let mut dict = HashMap::new();
 
-    let stone_count = process(&stones, &mut dict, 25);
-    println!("25 blinks {} stones", stone_count);
+    for blink_count in config.blink_counts.iter() {
+        match process(&rules, &stones, &mut dict, *blink_count) {
+            Ok(stone_count) => println!("{} blinks {} stones", blink_count, stone_count),
+            Err(err) => eprintln!("{} blinks failed: {}", blink_count, err),
+        }
+    }
 
-    let stone_count = process(&stones, &mut dict, 75);
-    println!("75 blinks {} stones", stone_count);
+    if let Some(analytics_file) = config.analytics_file {
+        let blink_count = config.blink_counts.into_iter().max().unwrap_or(0);
+        let stats =
+            analyse(&rules, &stones, &mut dict, blink_count, config.top).unwrap_or_else(|err| {
+                eprintln!("Problem analysing stones: {}", err);
+                std::process::exit(1);
+            });
+        match get_saturation_blink(&stats) {
+            Some(blink) => println!("Distinct stone values stop growing at blink {}", blink),
+            None => println!("Distinct stone values still grow at blink {}", blink_count),
+        }
+        fs::write(&analytics_file, to_csv(&stats)).unwrap_or_else(|err| {
+            eprintln!("Problem writing analytics file: {}", err);
+            std::process::exit(1);
+        });
+        println!("Analytics written to {}", analytics_file);
+    }
 }
 
 #[cfg(test)]
@@ -89,17 +376,103 @@ mod tests {
 
     #[test]
     fn test_process_ex() {
+        let rules = Rules::default();
         let stones = to_stones(&read_input_file("input/day11_ex.txt"));
-        let mut dict: HashMap<(usize, u8), usize> = HashMap::new();
-        assert_eq!(process(&stones, &mut dict, 25), 55312);
-        assert_eq!(process(&stones, &mut dict, 75), 65601038650482);
+        let mut dict = HashMap::new();
+        assert_eq!(process(&rules, &stones, &mut dict, 25), Ok(55312));
+        assert_eq!(process(&rules, &stones, &mut dict, 75), Ok(65601038650482));
+    }
+
+    #[test]
+    fn test_rules() {
+        let puzzle_rules = Rules::from_str("0 -> 1\neven_digits -> split\n* -> mul 2024").unwrap();
+        for stone in [0, 1, 99, 125, 1000, 253000] {
+            assert_eq!(puzzle_rules.blink(stone), Rules::default().blink(stone));
+        }
+        let stones = to_stones(&read_input_file("input/day11_ex.txt"));
+        let mut dict = HashMap::new();
+        assert_eq!(process(&puzzle_rules, &stones, &mut dict, 25), Ok(55312));
+
+        let rules = Rules::from_str(
+            "# halve even stones, duplicate the rest
+            div 2 -> split
+            7 -> x, x, 1
+            odd_digits -> add 1",
+        )
+        .unwrap();
+        assert_eq!(rules.blink(1234), Ok(vec![12, 34]));
+        assert_eq!(rules.blink(7), Ok(vec![7, 7, 1]));
+        assert_eq!(rules.blink(123), Ok(vec![124]));
+        assert_eq!(rules.blink(13), Ok(vec![13]));
+        assert_eq!(rules.blink(2), Ok(vec![2, 0]));
+        assert_eq!(rules.blink(100), Ok(vec![10, 0]));
+        let mut dict = HashMap::new();
+        assert_eq!(process(&rules, &[7], &mut dict, 2), Ok(7));
+
+        let closures = Rules(vec![
+            Rule::new(|stone| stone < 10, |stone| Some(vec![stone + 10])),
+            Rule::new(|_| true, split_digits),
+        ]);
+        assert_eq!(closures.blink(5), Ok(vec![15]));
+        let mut dict = HashMap::new();
+        assert_eq!(process(&closures, &[5], &mut dict, 3), Ok(2));
+
+        assert!(Rules::from_str("0 => 1").is_err());
+        assert!(Rules::from_str("div 0 -> 1").is_err());
+        assert!(Rules::from_str("* -> pow 2").is_err());
+    }
+
+    #[test]
+    fn test_big_numbers() {
+        assert_eq!(split_digits(1000), Some(vec![10, 0]));
+        assert_eq!(split_digits(10000001), Some(vec![1000, 1]));
+        assert_eq!(split_digits(u64::MAX), Some(vec![1844674407, 3709551615]));
+
+        let rules = Rules::default();
+        assert!(rules.blink(u64::MAX / 10).is_err());
+        let mut dict = HashMap::new();
+        assert!(process(&rules, &[u64::MAX / 1000], &mut dict, 1).is_err());
+
+        let stones = to_stones(&read_input_file("input/day11_ex.txt"));
+        let mut dict = HashMap::new();
+        let stone_count = process(&rules, &stones, &mut dict, 200).unwrap();
+        assert!(stone_count > u64::MAX as u128);
+        assert!(process(&rules, &stones, &mut dict, 1000).is_err());
+    }
+
+    #[test]
+    fn test_analyse() {
+        let rules = Rules::default();
+        let stones = to_stones(&read_input_file("input/day11_ex.txt"));
+        let mut dict = HashMap::new();
+        let stats = analyse(&rules, &stones, &mut dict, 6, 2).unwrap();
+        assert_eq!(stats.len(), 7);
+        assert_eq!(stats[6].stones, 22);
+        assert_eq!(get_saturation_blink(&stats), None);
+        let csv = to_csv(&stats);
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("blink,stones,distinct,new_values,seen_values,most_frequent")
+        );
+        assert_eq!(lines.next(), Some("0,2,2,2,2,17:1 125:1"));
+        assert_eq!(lines.next(), Some("1,3,3,3,5,1:1 7:1"));
+        assert_eq!(lines.nth(1), Some("3,5,5,4,13,1:1 20:1"));
+
+        let stats = analyse(&rules, &stones, &mut dict, 100, 1).unwrap();
+        let saturation_blink = get_saturation_blink(&stats).unwrap();
+        assert!(stats[saturation_blink - 1].new_values > 0);
+        assert!(stats[saturation_blink..]
+            .iter()
+            .all(|stat| stat.new_values == 0));
     }
 
     #[test]
     fn test_process() {
+        let rules = Rules::default();
         let stones = to_stones(&read_input_file("input/day11.txt"));
-        let mut dict: HashMap<(usize, u8), usize> = HashMap::new();
-        assert_eq!(process(&stones, &mut dict, 25), 198089);
-        assert_eq!(process(&stones, &mut dict, 75), 236302670835517);
+        let mut dict = HashMap::new();
+        assert_eq!(process(&rules, &stones, &mut dict, 25), Ok(198089));
+        assert_eq!(process(&rules, &stones, &mut dict, 75), Ok(236302670835517));
     }
 }
@@ -5,6 +5,16 @@ use std::{
 
 struct Config {
     in_file: String,
+    show_ids: bool,
+    svg_file: Option<String>,
+    pricing: Option<String>,
+    rates_file: Option<String>,
+    breakdown: Option<BreakdownFormat>,
+}
+
+enum BreakdownFormat {
+    Table,
+    Json,
 }
 
 impl Config {
@@ -13,8 +23,41 @@ impl Config {
             Some(arg) => arg,
             None => return Err("Missing input file argument"),
         };
+        let mut show_ids = false;
+        let mut svg_file = None;
+        let mut pricing = None;
+        let mut rates_file = None;
+        let mut breakdown = None;
+        while let Some(option) = args.next() {
+            let mut value = || args.next().ok_or("Missing option value");
+            match option.as_str() {
+                "--ids" => show_ids = true,
+                "--svg" => svg_file = Some(value()?),
+                "--pricing" => pricing = Some(value()?),
+                "--rates" => rates_file = Some(value()?),
+                "--breakdown" => {
+                    breakdown = match value()?.as_str() {
+                        "table" => Some(BreakdownFormat::Table),
+                        "json" => Some(BreakdownFormat::Json),
+                        _ => return Err("Breakdown must be table or json"),
+                    }
+                }
+                _ => {
+                    return Err(
+                        "Unknown option, expected --ids, --svg, --pricing, --rates or --breakdown",
+                    )
+                }
+            }
+        }
 
-        Ok(Config { in_file })
+        Ok(Config {
+            in_file,
+            show_ids,
+            svg_file,
+            pricing,
+            rates_file,
+            breakdown,
+        })
     }
 }
 
@@ -25,184 +68,560 @@ fn read_input_file(file_path: &str) -> String {
     })
 }
 
-type Position = (u8, u8);
+type Position = (usize, usize);
 
-struct Region<'a> {
-    plant_type: char,
-    plots: HashSet<&'a Position>,
+// the garden as a dense grid, every plot labelled with the id of its region
+struct Garden {
+    width: usize,
+    height: usize,
+    plants: Vec<char>,
+    labels: Vec<usize>,
 }
 
-fn to_plots(raw_dataset: &str) -> HashMap<Position, char> {
-    raw_dataset
-        .lines()
-        .enumerate()
-        .fold(HashMap::new(), |mut acc, (row, line)| {
-            line.chars().enumerate().for_each(|(col, c)| {
-                acc.insert((row as u8, col as u8), c);
-            });
-            acc
-        })
-}
+impl Garden {
+    fn new(raw_dataset: &str) -> Result<Garden, &'static str> {
+        let width = raw_dataset
+            .lines()
+            .next()
+            .map_or(0, |line| line.chars().count());
+        let mut plants = Vec::new();
+        for line in raw_dataset.lines() {
+            let row_start = plants.len();
+            plants.extend(line.chars());
+            if plants.len() - row_start != width {
+                return Err("Garden rows must all have the same width");
+            }
+        }
+        let height = plants.len() / width.max(1);
+        let mut garden = Garden {
+            width,
+            height,
+            plants,
+            labels: Vec::new(),
+        };
+        garden.labels = garden.label_regions();
+        Ok(garden)
+    }
 
-fn expand_region<'a>(
-    region: &mut Region<'a>,
-    plots: &'a HashMap<Position, char>,
-    taken: &mut HashSet<&'a Position>,
-    position: &'a Position,
-) {
-    if taken.contains(position)
-        || !plots.contains_key(&position)
-        || plots[&position] != region.plant_type
-    {
-        return;
-    }
-    taken.insert(position);
-    region.plots.insert(position);
-    let (row, col) = position;
-    for row_offset in [row.checked_sub(1), row.checked_add(1)]
-        .into_iter()
-        .filter_map(|y| y)
-    {
-        let pos = &(row_offset, *col);
-        if let Some((pos, _)) = plots.get_key_value(pos) {
-            expand_region(region, plots, taken, pos);
+    fn get_index(&self, row: Option<usize>, col: Option<usize>) -> Option<usize> {
+        match (row, col) {
+            (Some(row), Some(col)) if row < self.height && col < self.width => {
+                Some(row * self.width + col)
+            }
+            _ => None,
         }
     }
-    for col_offset in [col.checked_sub(1), col.checked_add(1)]
-        .into_iter()
-        .filter_map(|x| x)
-    {
-        let pos = &(*row, col_offset);
-        if let Some((pos, _)) = plots.get_key_value(pos) {
-            expand_region(region, plots, taken, pos);
+
+    fn get_label(&self, row: Option<usize>, col: Option<usize>) -> Option<usize> {
+        self.get_index(row, col).map(|index| self.labels[index])
+    }
+
+    // iterative flood fill in reading order, so region ids follow their top left plot
+    fn label_regions(&self) -> Vec<usize> {
+        let mut labels = vec![usize::MAX; self.plants.len()];
+        let mut next_label = 0;
+        let mut stack = Vec::new();
+        for start in 0..self.plants.len() {
+            if labels[start] != usize::MAX {
+                continue;
+            }
+            labels[start] = next_label;
+            stack.push(start);
+            while let Some(index) = stack.pop() {
+                let (row, col) = (index / self.width, index % self.width);
+                let neighbors = [
+                    self.get_index(row.checked_sub(1), Some(col)),
+                    self.get_index(Some(row + 1), Some(col)),
+                    self.get_index(Some(row), col.checked_sub(1)),
+                    self.get_index(Some(row), Some(col + 1)),
+                ];
+                for neighbor in neighbors.into_iter().flatten() {
+                    if labels[neighbor] == usize::MAX && self.plants[neighbor] == self.plants[start]
+                    {
+                        labels[neighbor] = next_label;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            next_label += 1;
+        }
+        labels
+    }
+
+    fn to_regions(&self) -> Vec<Region<'_>> {
+        let region_count = self.labels.iter().max().map_or(0, |label| label + 1);
+        let mut regions: Vec<Region> = (0..region_count)
+            .map(|id| Region {
+                id,
+                plant_type: ' ',
+                plots: Vec::new(),
+                garden: self,
+            })
+            .collect();
+        for (index, label) in self.labels.iter().enumerate() {
+            let region = &mut regions[*label];
+            region.plant_type = self.plants[index];
+            region.plots.push((index / self.width, index % self.width));
         }
+        regions
     }
 }
 
-fn to_regions(
-    plots: &HashMap<Position, char>,
-) -> Vec<Region> {
-    let mut taken_plots: HashSet<&Position> = HashSet::new();
-    let regions: Vec<Region> = plots
-        .iter()
-        .fold(Vec::new(), |mut acc, (position, plant_type)| {
-            if taken_plots.contains(position) {
-                return acc;
+struct Region<'a> {
+    id: usize,
+    plant_type: char,
+    plots: Vec<Position>,
+    garden: &'a Garden,
+}
+
+impl Region<'_> {
+    fn contains(&self, row: Option<usize>, col: Option<usize>) -> bool {
+        self.garden.get_label(row, col) == Some(self.id)
+    }
+}
+
+// a lattice point between plots, plot (row, col) spans corners (row, col) to (row + 1, col + 1)
+type Corner = (usize, usize);
+
+struct Polygon {
+    corners: Vec<Corner>,
+    is_hole: bool,
+}
+
+impl Polygon {
+    // twice the signed area, positive for outer boundaries and negative for holes
+    fn get_double_area(corners: &[Corner]) -> i64 {
+        corners
+            .iter()
+            .zip(corners.iter().cycle().skip(1))
+            .map(|((row, col), (next_row, next_col))| {
+                *col as i64 * *next_row as i64 - *next_col as i64 * *row as i64
+            })
+            .sum()
+    }
+}
+
+impl Region<'_> {
+    // boundary edges run clockwise with the region on their right, so outer boundaries come out
+    // clockwise and holes counter-clockwise
+    fn get_boundary_edges(&self) -> HashMap<Corner, Vec<Corner>> {
+        let mut edges: HashMap<Corner, Vec<Corner>> = HashMap::new();
+        for &(row, col) in self.plots.iter() {
+            let (bottom, right) = (row + 1, col + 1);
+            let sides = [
+                ((row.checked_sub(1), Some(col)), (row, col), (row, right)),
+                ((Some(row), Some(right)), (row, right), (bottom, right)),
+                ((Some(bottom), Some(col)), (bottom, right), (bottom, col)),
+                ((Some(row), col.checked_sub(1)), (bottom, col), (row, col)),
+            ];
+            for ((neighbor_row, neighbor_col), from, to) in sides {
+                if !self.contains(neighbor_row, neighbor_col) {
+                    edges.entry(from).or_default().push(to);
+                }
             }
-            let mut region = Region {
-                plant_type: *plant_type,
-                plots: HashSet::new(),
-            };
-            expand_region(&mut region, &plots, &mut taken_plots, position);
+        }
+        edges
+    }
 
-            acc.push(region);
-            acc
-        });
-    regions
+    // closed polygons of corner points, where two plots of the region only touch diagonally the
+    // boundary turns left, keeping the region connected and holes touching at a corner apart
+    fn get_outline(&self) -> Vec<Polygon> {
+        let mut edges = self.get_boundary_edges();
+        let mut polygons = Vec::new();
+        while let Some(start) = edges.keys().min().copied() {
+            let mut points = vec![start];
+            let mut direction = (0i64, 1i64);
+            let mut current = start;
+            loop {
+                let outgoing = edges.get_mut(&current).unwrap();
+                let step = |to: &Corner| {
+                    (
+                        to.0 as i64 - current.0 as i64,
+                        to.1 as i64 - current.1 as i64,
+                    )
+                };
+                let left_turn = (-direction.1, direction.0);
+                let i = outgoing
+                    .iter()
+                    .position(|to| step(to) == left_turn)
+                    .or_else(|| outgoing.iter().position(|to| step(to) == direction))
+                    .unwrap_or(0);
+                let next = outgoing.swap_remove(i);
+                if outgoing.is_empty() {
+                    edges.remove(&current);
+                }
+                direction = step(&next);
+                current = next;
+                if current == start {
+                    break;
+                }
+                points.push(current);
+            }
+            // only keep the points where the boundary changes direction
+            let corners: Vec<Corner> = (0..points.len())
+                .filter(|i| {
+                    let previous = points[(i + points.len() - 1) % points.len()];
+                    let next = points[(i + 1) % points.len()];
+                    previous.0 != next.0 && previous.1 != next.1
+                })
+                .map(|i| points[i])
+                .collect();
+            polygons.push(Polygon {
+                is_hole: Polygon::get_double_area(&corners) < 0,
+                corners,
+            });
+        }
+        polygons
+    }
 }
 
-fn count_perimeter(region: &Region) -> u8 {
+fn count_perimeter(region: &Region) -> usize {
     region
         .plots
         .iter()
-        .map(|position| {
-            let (row, col) = position;
-            let row_iter = [row.wrapping_sub(1), row.wrapping_add(1)]
-                .into_iter()
-                .map(|row_offset| (row_offset, *col));
-            let col_iter = [col.wrapping_sub(1), col.wrapping_add(1)]
-                .into_iter()
-                .map(|col_offset| (*row, col_offset));
-            row_iter
-                .chain(col_iter)
-                .filter(|neighbor_position| !region.plots.contains(neighbor_position))
-                .count() as u8
+        .map(|&(row, col)| {
+            [
+                (row.checked_sub(1), Some(col)),
+                (Some(row + 1), Some(col)),
+                (Some(row), col.checked_sub(1)),
+                (Some(row), Some(col + 1)),
+            ]
+            .into_iter()
+            .filter(|(row, col)| !region.contains(*row, *col))
+            .count()
         })
         .sum()
 }
 
 fn price_by_perimeters(region: &Region) -> usize {
-    region.plots.len() * count_perimeter(region) as usize
+    region.plots.len() * count_perimeter(region)
 }
 
 fn total_price_by_perimeters(regions: &[Region]) -> usize {
     regions.iter().map(price_by_perimeters).sum()
 }
 
-fn count_side(region: &Region) -> u8 {
-    let top_perimeter: HashSet<&Position> = region
-        .plots
-        .iter()
-        .filter(|(row, col)| !region.plots.contains(&(row.wrapping_sub(1), *col)))
-        .map(|p| *p)
-        .collect();
-    let bottom_perimeter: HashSet<&Position> = region
-        .plots
-        .iter()
-        .filter(|(row, col)| !region.plots.contains(&(row.wrapping_add(1), *col)))
-        .map(|p| *p)
-        .collect();
-    let left_perimeter: HashSet<&Position> = region
-        .plots
-        .iter()
-        .filter(|(row, col)| !region.plots.contains(&(*row, col.wrapping_sub(1))))
-        .map(|p| *p)
-        .collect();
-    let right_perimeter: HashSet<&Position> = region
+// a polygon has as many sides as corners, so count the convex and concave corners of each plot
+fn count_side(region: &Region) -> usize {
+    region
         .plots
         .iter()
-        .filter(|(row, col)| !region.plots.contains(&(*row, col.wrapping_add(1))))
-        .map(|p| *p)
-        .collect();
-    let top_side: HashMap<u8, HashSet<u8>> =
-        top_perimeter
-            .iter()
-            .fold(HashMap::new(), |mut acc, (row, col)| {
-                acc.entry(*row).or_insert(HashSet::new()).insert(*col);
-                acc
-            });
-    let bottom_side: HashMap<u8, HashSet<u8>> =
-        bottom_perimeter
-            .iter()
-            .fold(HashMap::new(), |mut acc, (row, col)| {
-                acc.entry(*row).or_insert(HashSet::new()).insert(*col);
-                acc
-            });
-    let left_side: HashMap<u8, HashSet<u8>> =
-        left_perimeter
-            .iter()
-            .fold(HashMap::new(), |mut acc, (row, col)| {
-                acc.entry(*col).or_insert(HashSet::new()).insert(*row);
-                acc
-            });
-    let right_side: HashMap<u8, HashSet<u8>> =
-        right_perimeter
-            .iter()
-            .fold(HashMap::new(), |mut acc, (row, col)| {
-                acc.entry(*col).or_insert(HashSet::new()).insert(*row);
-                acc
-            });
-    [top_side, bottom_side, left_side, right_side]
-        .iter()
-        .map(|side| {
-            side.iter()
-                .map(|(_, plot_sides)| {
-                    plot_sides.iter()
-                        .filter(|plot_side| !plot_sides.contains(&plot_side.wrapping_add(1)))
-                        .count() as u8
-                }).sum::<u8>()
+        .map(|&(row, col)| {
+            let rows = [row.checked_sub(1), Some(row + 1)];
+            let cols = [col.checked_sub(1), Some(col + 1)];
+            rows.into_iter()
+                .flat_map(|corner_row| {
+                    cols.into_iter()
+                        .map(move |corner_col| (corner_row, corner_col))
+                })
+                .filter(|(corner_row, corner_col)| {
+                    let vertical = region.contains(*corner_row, Some(col));
+                    let horizontal = region.contains(Some(row), *corner_col);
+                    let diagonal = region.contains(*corner_row, *corner_col);
+                    (!vertical && !horizontal) || (vertical && horizontal && !diagonal)
+                })
+                .count()
         })
-        .sum::<u8>()
+        .sum()
 }
 
 fn price_by_sides(region: &Region) -> usize {
-    region.plots.len() * count_side(region) as usize
+    region.plots.len() * count_side(region)
 }
 
 fn total_price_by_sides(regions: &[Region]) -> usize {
     regions.iter().map(price_by_sides).sum()
 }
 
+trait PricingModel {
+    fn get_name(&self) -> String;
+    fn get_price(&self, region: &Region) -> f64;
+}
+
+struct PerimeterPricing;
+
+impl PricingModel for PerimeterPricing {
+    fn get_name(&self) -> String {
+        "perimeter".to_string()
+    }
+
+    fn get_price(&self, region: &Region) -> f64 {
+        price_by_perimeters(region) as f64
+    }
+}
+
+struct SidePricing;
+
+impl PricingModel for SidePricing {
+    fn get_name(&self) -> String {
+        "sides".to_string()
+    }
+
+    fn get_price(&self, region: &Region) -> f64 {
+        price_by_sides(region) as f64
+    }
+}
+
+// one fence post per distinct corner point, two sides meeting at a pinch share their post
+struct CornerPricing;
+
+impl PricingModel for CornerPricing {
+    fn get_name(&self) -> String {
+        "corners".to_string()
+    }
+
+    fn get_price(&self, region: &Region) -> f64 {
+        let corners: HashSet<Corner> = region
+            .get_outline()
+            .into_iter()
+            .flat_map(|polygon| polygon.corners)
+            .collect();
+        (region.plots.len() * corners.len()) as f64
+    }
+}
+
+fn get_convex_hull_perimeter(region: &Region) -> f64 {
+    let mut points: Vec<(i64, i64)> = region
+        .get_outline()
+        .into_iter()
+        .filter(|polygon| !polygon.is_hole)
+        .flat_map(|polygon| polygon.corners)
+        .map(|(row, col)| (col as i64, row as i64))
+        .collect();
+    points.sort();
+    points.dedup();
+    let cross = |o: (i64, i64), a: (i64, i64), b: (i64, i64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    // monotone chain, the lower then the upper half of the hull
+    let mut hull: Vec<(i64, i64)> = Vec::new();
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let half_start = hull.len();
+        for point in pass {
+            while hull.len() >= half_start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+    }
+    hull.iter()
+        .zip(hull.iter().cycle().skip(1))
+        .map(|(a, b)| (((b.0 - a.0).pow(2) + (b.1 - a.1).pow(2)) as f64).sqrt())
+        .sum()
+}
+
+struct ConvexHullPricing;
+
+impl PricingModel for ConvexHullPricing {
+    fn get_name(&self) -> String {
+        "convex hull".to_string()
+    }
+
+    fn get_price(&self, region: &Region) -> f64 {
+        region.plots.len() as f64 * get_convex_hull_perimeter(region)
+    }
+}
+
+// multiplies the price of another model by a rate per plant type
+struct RateTablePricing {
+    rates: HashMap<char, f64>,
+    default_rate: f64,
+    base: Box<dyn PricingModel>,
+}
+
+impl RateTablePricing {
+    // one `PLANT RATE` pair per line, `*` sets the rate of unlisted plants
+    fn new(raw_rates: &str, base: Box<dyn PricingModel>) -> Result<Self, &'static str> {
+        let mut rates = HashMap::new();
+        let mut default_rate = 1.0;
+        for line in raw_rates
+            .lines()
+            .map(|line| line.split('#').next().unwrap().trim())
+            .filter(|line| !line.is_empty())
+        {
+            let error = "Rates must be lines of a plant type and a rate like `A 1.5`";
+            let (plant, rate) = line.split_once(char::is_whitespace).ok_or(error)?;
+            let rate = match rate.trim().parse::<f64>() {
+                Ok(rate) if rate.is_finite() && rate >= 0.0 => rate,
+                _ => return Err(error),
+            };
+            let mut plant_chars = plant.chars();
+            match (plant_chars.next(), plant_chars.next()) {
+                (Some('*'), None) => default_rate = rate,
+                (Some(plant_type), None) => {
+                    rates.insert(plant_type, rate);
+                }
+                _ => return Err(error),
+            }
+        }
+        Ok(RateTablePricing {
+            rates,
+            default_rate,
+            base,
+        })
+    }
+}
+
+impl PricingModel for RateTablePricing {
+    fn get_name(&self) -> String {
+        format!("{} with rates", self.base.get_name())
+    }
+
+    fn get_price(&self, region: &Region) -> f64 {
+        let rate = self
+            .rates
+            .get(&region.plant_type)
+            .unwrap_or(&self.default_rate);
+        rate * self.base.get_price(region)
+    }
+}
+
+fn to_pricing_model(name: &str) -> Result<Box<dyn PricingModel>, &'static str> {
+    match name {
+        "perimeter" => Ok(Box::new(PerimeterPricing)),
+        "sides" => Ok(Box::new(SidePricing)),
+        "corners" => Ok(Box::new(CornerPricing)),
+        "hull" => Ok(Box::new(ConvexHullPricing)),
+        _ => Err("Pricing must be perimeter, sides, corners or hull"),
+    }
+}
+
+fn total_price(regions: &[Region], model: &dyn PricingModel) -> f64 {
+    regions.iter().map(|region| model.get_price(region)).sum()
+}
+
+struct RegionBreakdown {
+    id: usize,
+    plant_type: char,
+    area: usize,
+    perimeter: usize,
+    sides: usize,
+    price: f64,
+}
+
+fn get_breakdown(regions: &[Region], model: &dyn PricingModel) -> Vec<RegionBreakdown> {
+    regions
+        .iter()
+        .map(|region| RegionBreakdown {
+            id: region.id,
+            plant_type: region.plant_type,
+            area: region.plots.len(),
+            perimeter: count_perimeter(region),
+            sides: count_side(region),
+            price: model.get_price(region),
+        })
+        .collect()
+}
+
+fn format_breakdown_table(breakdown: &[RegionBreakdown]) -> String {
+    let mut table = format!(
+        "{:>6} {:>4} {:>8} {:>9} {:>6} {:>12}",
+        "id", "type", "area", "perimeter", "sides", "price"
+    );
+    for region in breakdown {
+        table.push_str(&format!(
+            "\n{:>6} {:>4} {:>8} {:>9} {:>6} {:>12.2}",
+            region.id, region.plant_type, region.area, region.perimeter, region.sides, region.price
+        ));
+    }
+    table
+}
+
+// non-ASCII characters are valid in JSON strings as they are
+fn escape_json(raw: &str) -> String {
+    raw.chars()
+        .map(|c| match c {
+            '"' => "\\\"".to_string(),
+            '\\' => "\\\\".to_string(),
+            c if c.is_control() => format!("\\u{:04x}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn format_breakdown_json(breakdown: &[RegionBreakdown]) -> String {
+    let regions = breakdown
+        .iter()
+        .map(|region| {
+            format!(
+                "  {{\"id\": {}, \"type\": \"{}\", \"area\": {}, \"perimeter\": {}, \"sides\": {}, \"price\": {}}}",
+                region.id,
+                escape_json(&region.plant_type.to_string()),
+                region.area,
+                region.perimeter,
+                region.sides,
+                region.price
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",\n");
+    format!("[\n{}\n]", regions)
+}
+
+fn render_region_ids(garden: &Garden) -> String {
+    let region_count = garden.labels.iter().max().map_or(0, |label| label + 1);
+    let id_width = region_count.saturating_sub(1).to_string().len();
+    garden
+        .labels
+        .chunks(garden.width.max(1))
+        .map(|row| {
+            row.iter()
+                .map(|label| format!("{:>id_width$}", label))
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+const SVG_PLOT_SIZE: usize = 10;
+
+fn to_svg(garden: &Garden, regions: &[Region]) -> String {
+    let (width, height) = (garden.width, garden.height);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width * SVG_PLOT_SIZE,
+        height * SVG_PLOT_SIZE,
+        width,
+        height
+    );
+    for region in regions.iter() {
+        let outline = region.get_outline();
+        let path = outline
+            .iter()
+            .map(|polygon| {
+                let points = polygon
+                    .corners
+                    .iter()
+                    .map(|(row, col)| format!("{} {}", col, row))
+                    .collect::<Vec<String>>()
+                    .join(" L ");
+                format!("M {} Z", points)
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        let sides: usize = outline.iter().map(|polygon| polygon.corners.len()).sum();
+        let holes = outline.iter().filter(|polygon| polygon.is_hole).count();
+        // spread the plant types over the hue circle
+        let hue = (region.plant_type as u32 * 47) % 360;
+        svg.push_str(&format!(
+            "  <path d=\"{}\" fill=\"hsl({}, 60%, 70%)\" fill-rule=\"evenodd\" stroke=\"black\" stroke-width=\"0.05\"><title>{} {}: {} plots, {} sides, {} holes</title></path>\n",
+            path,
+            hue,
+            region.id,
+            region.plant_type,
+            region.plots.len(),
+            sides,
+            holes
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
 pub fn run(mut args: impl Iterator<Item = String>) {
     let config = Config::new(&mut args).unwrap_or_else(|err| {
         eprintln!("Problem parsing arguments: {}", err);
@@ -212,15 +631,61 @@ pub fn run(mut args: impl Iterator<Item = String>) {
 
     let raw_dataset = read_input_file(&config.in_file);
 
-    let plots = to_plots(&raw_dataset);
+    let garden = Garden::new(&raw_dataset).unwrap_or_else(|err| {
+        eprintln!("Problem parsing garden: {}", err);
+        std::process::exit(1);
+    });
 
-    let regions = to_regions(&plots);
+    let regions = garden.to_regions();
 
     let price_by_perimeters = total_price_by_perimeters(&regions);
     println!("Price by perimeter: {}", price_by_perimeters);
 
     let price_by_sides = total_price_by_sides(&regions);
     println!("Price by sides: {}", price_by_sides);
+
+    if config.pricing.is_some() || config.rates_file.is_some() || config.breakdown.is_some() {
+        let mut model = to_pricing_model(config.pricing.as_deref().unwrap_or("perimeter"))
+            .unwrap_or_else(|err| {
+                eprintln!("Problem parsing arguments: {}", err);
+                std::process::exit(1);
+            });
+        if let Some(rates_file) = config.rates_file {
+            let rate_table = RateTablePricing::new(&read_input_file(&rates_file), model)
+                .unwrap_or_else(|err| {
+                    eprintln!("Problem parsing rates: {}", err);
+                    std::process::exit(1);
+                });
+            model = Box::new(rate_table);
+        }
+        println!(
+            "Price by {}: {}",
+            model.get_name(),
+            total_price(&regions, model.as_ref())
+        );
+        match config.breakdown {
+            Some(BreakdownFormat::Table) => println!(
+                "{}",
+                format_breakdown_table(&get_breakdown(&regions, model.as_ref()))
+            ),
+            Some(BreakdownFormat::Json) => println!(
+                "{}",
+                format_breakdown_json(&get_breakdown(&regions, model.as_ref()))
+            ),
+            None => {}
+        }
+    }
+
+    if config.show_ids {
+        println!("{}", render_region_ids(&garden));
+    }
+    if let Some(svg_file) = config.svg_file {
+        fs::write(&svg_file, to_svg(&garden, &regions)).unwrap_or_else(|err| {
+            eprintln!("Problem writing SVG file: {}", err);
+            std::process::exit(1);
+        });
+        println!("Regions written to {}", svg_file);
+    }
 }
 
 #[cfg(test)]
@@ -230,19 +695,137 @@ mod tests {
     #[test]
     fn test_ex() {
         let raw_dataset = read_input_file("input/day12_ex.txt");
-        let plots = to_plots(&raw_dataset);
-        let regions = to_regions(&plots);
+        let garden = Garden::new(&raw_dataset).unwrap();
+        let regions = garden.to_regions();
         let price_by_perimeters = total_price_by_perimeters(&regions);
         let price_by_sides = total_price_by_sides(&regions);
         assert_eq!(price_by_perimeters, 1930);
         assert_eq!(price_by_sides, 1206);
     }
 
+    #[test]
+    fn test_outline() {
+        let raw_dataset = "AAAAAA\nAAABBA\nAAABBA\nABBAAA\nABBAAA\nAAAAAA";
+        let garden = Garden::new(raw_dataset).unwrap();
+        let regions = garden.to_regions();
+        assert_eq!(regions.len(), 3);
+        assert_eq!(total_price_by_sides(&regions), 368);
+        let outline = regions[0].get_outline();
+        assert_eq!(outline.len(), 3);
+        assert_eq!(outline[0].corners, vec![(0, 0), (0, 6), (6, 6), (6, 0)]);
+        assert!(!outline[0].is_hole);
+        assert_eq!(outline[1].corners, vec![(1, 3), (3, 3), (3, 5), (1, 5)]);
+        assert!(outline[1..].iter().all(|polygon| polygon.is_hole));
+        assert_eq!(
+            render_region_ids(&garden).lines().nth(1),
+            Some("0 0 0 1 1 0")
+        );
+        let svg = to_svg(&garden, &regions);
+        assert!(svg.contains("M 0 0 L 6 0 L 6 6 L 0 6 Z M 3 1 L 3 3 L 5 3 L 5 1 Z"));
+        assert!(svg.contains("<title>0 A: 28 plots, 12 sides, 2 holes</title>"));
+
+        for raw_dataset in [
+            read_input_file("input/day12_ex.txt"),
+            read_input_file("input/day12.txt"),
+        ] {
+            let garden = Garden::new(&raw_dataset).unwrap();
+            for region in garden.to_regions() {
+                let outline = region.get_outline();
+                let corners: usize = outline.iter().map(|polygon| polygon.corners.len()).sum();
+                assert_eq!(corners, count_side(&region));
+                let area: i64 = outline
+                    .iter()
+                    .map(|polygon| Polygon::get_double_area(&polygon.corners))
+                    .sum();
+                assert_eq!(area, 2 * region.plots.len() as i64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_garden() {
+        let line = "A".repeat(1000);
+        let raw_dataset = vec![line.as_str(); 1000].join("\n");
+        let garden = Garden::new(&raw_dataset).unwrap();
+        let regions = garden.to_regions();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(count_perimeter(&regions[0]), 4000);
+        assert_eq!(total_price_by_perimeters(&regions), 4_000_000_000);
+        assert_eq!(total_price_by_sides(&regions), 4_000_000);
+
+        // a comb: one spine row on top and 500 teeth of A between B columns
+        let raw_dataset = (0..1000)
+            .map(|row| match row {
+                0 => "A".repeat(1000),
+                _ => "AB".repeat(500),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        let garden = Garden::new(&raw_dataset).unwrap();
+        let regions = garden.to_regions();
+        assert_eq!(regions.len(), 501);
+        assert_eq!(regions[0].plots.len(), 1000 + 500 * 999);
+        assert_eq!(count_side(&regions[0]), 2002);
+        assert_eq!(count_side(&regions[1]), 4);
+
+        assert!(Garden::new("AAA\nAA").is_err());
+    }
+
+    #[test]
+    fn test_pricing() {
+        let raw_dataset = read_input_file("input/day12_ex.txt");
+        let garden = Garden::new(&raw_dataset).unwrap();
+        let regions = garden.to_regions();
+        assert_eq!(total_price(&regions, &PerimeterPricing), 1930.0);
+        assert_eq!(total_price(&regions, &SidePricing), 1206.0);
+
+        // two holes touching at a corner share one post
+        let garden = Garden::new("AAAAAA\nAAABBA\nAAABBA\nABBAAA\nABBAAA\nAAAAAA").unwrap();
+        let regions = garden.to_regions();
+        assert_eq!(SidePricing.get_price(&regions[0]), 28.0 * 12.0);
+        assert_eq!(CornerPricing.get_price(&regions[0]), 28.0 * 11.0);
+
+        let garden = Garden::new("AA\nAB").unwrap();
+        let regions = garden.to_regions();
+        let hull_perimeter = 6.0 + 2f64.sqrt();
+        assert!((get_convex_hull_perimeter(&regions[0]) - hull_perimeter).abs() < 1e-9);
+        assert_eq!(get_convex_hull_perimeter(&regions[1]), 4.0);
+        assert_eq!(to_pricing_model("hull").unwrap().get_name(), "convex hull");
+
+        let rates = RateTablePricing::new("# rates\nA 2\n* 0.5", Box::new(SidePricing)).unwrap();
+        assert_eq!(rates.get_price(&regions[0]), 2.0 * 3.0 * 6.0);
+        assert_eq!(rates.get_price(&regions[1]), 0.5 * 4.0);
+        assert_eq!(total_price(&regions, &rates), 38.0);
+        assert!(RateTablePricing::new("AB 2", Box::new(SidePricing)).is_err());
+        for raw_rates in ["A two", "A NaN", "A inf", "* -infinity", "B -1"] {
+            assert!(RateTablePricing::new(raw_rates, Box::new(SidePricing)).is_err());
+        }
+
+        let breakdown = get_breakdown(&regions, &rates);
+        assert_eq!(
+            format_breakdown_table(&breakdown).lines().nth(1),
+            Some("     0    A        3         8      6        36.00")
+        );
+        assert_eq!(
+            format_breakdown_json(&breakdown),
+            "[\n  {\"id\": 0, \"type\": \"A\", \"area\": 3, \"perimeter\": 8, \"sides\": 6, \"price\": 36},\n  \
+             {\"id\": 1, \"type\": \"B\", \"area\": 1, \"perimeter\": 4, \"sides\": 4, \"price\": 2}\n]"
+        );
+
+        let garden = Garden::new("'é\"\\").unwrap();
+        let json = format_breakdown_json(&get_breakdown(&garden.to_regions(), &SidePricing));
+        assert!(json.contains("\"type\": \"'\""));
+        assert!(json.contains("\"type\": \"é\""));
+        assert!(json.contains("\"type\": \"\\\"\""));
+        assert!(json.contains("\"type\": \"\\\\\""));
+        assert_eq!(escape_json("\t\u{1f}"), "\\u0009\\u001f");
+    }
+
     #[test]
     fn test_actual() {
         let raw_dataset = read_input_file("input/day12.txt");
-        let plots = to_plots(&raw_dataset);
-        let regions = to_regions(&plots);
+        let garden = Garden::new(&raw_dataset).unwrap();
+        let regions = garden.to_regions();
         let price_by_perimeters = total_price_by_perimeters(&regions);
         let price_by_sides = total_price_by_sides(&regions);
         assert_eq!(price_by_perimeters, 1477762);
@@ -1,8 +1,14 @@
 use regex_lite::Regex;
-use std::fs;
+use std::{collections::HashMap, fs};
+
+const PART_ONE_PRESS_LIMIT: usize = 100;
+const PART_TWO_PRIZE_OFFSET: usize = 10000000000000;
 
 struct Config {
     in_file: String,
+    costs: HashMap<char, usize>,
+    prize_offset: Option<usize>,
+    press_limit: Option<usize>,
 }
 
 impl Config {
@@ -11,8 +17,36 @@ impl Config {
             Some(arg) => arg,
             None => return Err("Missing input file argument"),
         };
+        let mut config = Config {
+            in_file,
+            costs: HashMap::from([('A', 3), ('B', 1)]),
+            prize_offset: None,
+            press_limit: None,
+        };
+        while let Some(option) = args.next() {
+            let value = match args.next().map(|arg| arg.parse::<usize>()) {
+                Some(Ok(value)) => value,
+                Some(Err(_)) => return Err("Option value must be a non-negative number"),
+                None => return Err("Missing option value"),
+            };
+            match option.as_str() {
+                "--offset" => config.prize_offset = Some(value),
+                "--press-limit" => config.press_limit = Some(value),
+                option => {
+                    let mut label = option.strip_prefix("--cost-").unwrap_or_default().chars();
+                    match (label.next(), label.next()) {
+                        (Some(label), None) => {
+                            config.costs.insert(label.to_ascii_uppercase(), value);
+                        }
+                        _ => return Err(
+                            "Unknown option, expected --cost-{button}, --offset or --press-limit",
+                        ),
+                    }
+                }
+            }
+        }
 
-        Ok(Config { in_file })
+        Ok(config)
     }
 }
 
@@ -23,130 +57,246 @@ fn read_input_file(file_path: &str) -> String {
     })
 }
 
+struct Button {
+    label: char,
+    x: usize,
+    y: usize,
+    cost: usize,
+}
+
 struct Machine {
-    xa: usize,
-    ya: usize,
-    xb: usize,
-    yb: usize,
+    buttons: Vec<Button>,
     xp: usize,
     yp: usize,
 }
 
-fn to_machines(raw_dataset: &str) -> Vec<Machine> {
-    let regex = Regex::new(r"Button A: X\+(?<xa>\d+), Y\+(?<ya>\d+)\nButton B: X\+(?<xb>\d+), Y\+(?<yb>\d+)\nPrize: X=(?<xp>\d+), Y=(?<yp>\d+)").unwrap();
-    regex
-        .captures_iter(raw_dataset)
-        .map(|capture| {
-            let xa = capture
-                .name("xa")
-                .unwrap()
-                .as_str()
-                .parse::<usize>()
-                .unwrap();
-            let ya = capture
-                .name("ya")
-                .unwrap()
-                .as_str()
-                .parse::<usize>()
-                .unwrap();
-            let xb = capture
-                .name("xb")
-                .unwrap()
-                .as_str()
-                .parse::<usize>()
-                .unwrap();
-            let yb = capture
-                .name("yb")
-                .unwrap()
-                .as_str()
-                .parse::<usize>()
-                .unwrap();
-            let xp = capture
-                .name("xp")
-                .unwrap()
-                .as_str()
-                .parse::<usize>()
-                .unwrap();
-            let yp = capture
-                .name("yp")
-                .unwrap()
-                .as_str()
-                .parse::<usize>()
-                .unwrap();
-            Machine {
-                xa,
-                ya,
-                xb,
-                yb,
-                xp,
-                yp,
-            }
+fn to_machines(
+    raw_dataset: &str,
+    costs: &HashMap<char, usize>,
+) -> Result<Vec<Machine>, &'static str> {
+    let button_regex = Regex::new(r"Button (?<label>\w): X\+(?<x>\d+), Y\+(?<y>\d+)").unwrap();
+    let prize_regex = Regex::new(r"Prize: X=(?<xp>\d+), Y=(?<yp>\d+)").unwrap();
+    let to_number = |capture: &regex_lite::Captures, name: &str| -> usize {
+        capture.name(name).unwrap().as_str().parse().unwrap()
+    };
+    raw_dataset
+        .split("\n\n")
+        .filter(|raw_machine| !raw_machine.trim().is_empty())
+        .map(|raw_machine| {
+            let buttons = button_regex
+                .captures_iter(raw_machine)
+                .map(|capture| {
+                    let label = capture["label"]
+                        .chars()
+                        .next()
+                        .unwrap()
+                        .to_ascii_uppercase();
+                    let cost = *costs.get(&label).ok_or("Missing cost for button")?;
+                    Ok(Button {
+                        label,
+                        x: to_number(&capture, "x"),
+                        y: to_number(&capture, "y"),
+                        cost,
+                    })
+                })
+                .collect::<Result<Vec<Button>, &'static str>>()?;
+            let prize = prize_regex
+                .captures(raw_machine)
+                .ok_or("Missing prize position")?;
+            Ok(Machine {
+                buttons,
+                xp: to_number(&prize, "xp"),
+                yp: to_number(&prize, "yp"),
+            })
         })
         .collect()
 }
 
-fn solve_min_spent(machine: &Machine, add_to_position: usize) -> Option<usize> {
-    let first_part = machine.xa * machine.yb;
-    let last_part = machine.xb * machine.ya;
-    let swap = first_part < last_part;
-    let det = match swap {
-        false => first_part - last_part,
-        true => last_part - first_part,
-    };
-    let xp = machine.xp as f64 + add_to_position as f64;
-    let yp = machine.yp as f64 + add_to_position as f64;
-    if det != 0 {
-        let x = match swap {
-            false => {
-                ((xp * machine.yb as f64) - (machine.xb as f64 * yp))
-                    / det as f64
-            }
-            true => {
-                ((machine.xb as f64 * yp) - (xp * machine.yb as f64))
-                    / det as f64
+struct Settings {
+    prize_offset: usize,
+    press_limit: Option<usize>,
+}
+
+struct Presses {
+    counts: Vec<usize>,
+    spent: usize,
+}
+
+const NO_BUTTON: Button = Button {
+    label: '-',
+    x: 0,
+    y: 0,
+    cost: 0,
+};
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, s, t) = extended_gcd(b, a % b);
+        (g, t, s - (a / b) * t)
+    }
+}
+
+// minimum cost of a * ca + b * cb = target with 0 <= a, b <= limit, coefficients are non-negative
+fn solve_min_spent_on_line(
+    (ca, cost_a): (i128, i128),
+    (cb, cost_b): (i128, i128),
+    target: i128,
+    press_limit: Option<usize>,
+) -> Option<(i128, i128)> {
+    match (ca, cb) {
+        (0, 0) => (target == 0).then_some((0, 0)),
+        (0, cb) => (target % cb == 0 && target >= 0).then_some((0, target / cb)),
+        (ca, 0) => (target % ca == 0 && target >= 0).then_some((target / ca, 0)),
+        (ca, cb) => {
+            let (g, s, t) = extended_gcd(ca, cb);
+            if target % g != 0 {
+                return None;
             }
-        };
-        let y = match swap {
-            false => {
-                ((machine.xa as f64 * yp) - (xp * machine.ya as f64))
-                    / det as f64
+            let (a0, b0) = (s * (target / g), t * (target / g));
+            let (step_a, step_b) = (cb / g, ca / g);
+            let mut k_min = -a0.div_euclid(step_a);
+            let mut k_max = b0.div_euclid(step_b);
+            if let Some(limit) = press_limit {
+                let limit = limit as i128;
+                k_min = k_min.max(-(limit - b0).div_euclid(step_b));
+                k_max = k_max.min((limit - a0).div_euclid(step_a));
             }
-            true => {
-                ((xp * machine.ya as f64) - (machine.xa as f64 * yp))
-                    / det as f64
+            if k_min > k_max {
+                return None;
             }
-        };
-        if x.fract() != 0.0 || y.fract() != 0.0 {
+            let k = match cost_a * step_a - cost_b * step_b >= 0 {
+                true => k_min,
+                false => k_max,
+            };
+            Some((a0 + k * step_a, b0 - k * step_b))
+        }
+    }
+}
+
+fn solve_pair(
+    button_a: &Button,
+    button_b: &Button,
+    xp: i128,
+    yp: i128,
+    press_limit: Option<usize>,
+) -> Option<(usize, usize)> {
+    let (xa, ya, cost_a) = (
+        button_a.x as i128,
+        button_a.y as i128,
+        button_a.cost as i128,
+    );
+    let (xb, yb, cost_b) = (
+        button_b.x as i128,
+        button_b.y as i128,
+        button_b.cost as i128,
+    );
+    let det = xa * yb - xb * ya;
+    let (a, b) = if det != 0 {
+        let a_numerator = xp * yb - xb * yp;
+        let b_numerator = xa * yp - xp * ya;
+        if a_numerator % det != 0 || b_numerator % det != 0 {
             return None;
-        } else {
-            Some((x.trunc() as usize * 3) + y.trunc() as usize)
         }
+        (a_numerator / det, b_numerator / det)
+    } else if xa != 0 || xb != 0 {
+        solve_min_spent_on_line((xa, cost_a), (xb, cost_b), xp, press_limit)?
     } else {
-        let x_ratio = machine.xa as f64 / machine.xb as f64;
-        let y_ratio = machine.ya as f64 / machine.yb as f64;
-        let p_ratio = xp / yp;
-        if x_ratio == y_ratio && y_ratio == p_ratio {
-            if x_ratio < 3f64 {
-                Some(machine.xp / machine.xa)
-            } else {
-                let b = (xp / machine.xb as f64).trunc() as usize;
-                let a = (xp - (b * machine.xb) as f64) / machine.xa as f64;
-                match a.fract() {
-                    0.0 => Some((a.trunc() as usize * 3) + b),
-                    _ => None,
-                }
-            }
-        } else {
-            None
+        solve_min_spent_on_line((ya, cost_a), (yb, cost_b), yp, press_limit)?
+    };
+    if a < 0 || b < 0 || a * xa + b * xb != xp || a * ya + b * yb != yp {
+        return None;
+    }
+    let (a, b) = (a as usize, b as usize);
+    if press_limit.is_some_and(|limit| a > limit || b > limit) {
+        return None;
+    }
+    Some((a, b))
+}
+
+// the last button is pressed every possible count, the remaining are solved recursively,
+// so more than two buttons are only searched within a press limit
+fn solve_buttons(
+    buttons: &[Button],
+    xp: i128,
+    yp: i128,
+    press_limit: Option<usize>,
+) -> Option<(usize, Vec<usize>)> {
+    match buttons {
+        [] => (xp == 0 && yp == 0).then(|| (0, Vec::new())),
+        [button] => solve_pair(button, &NO_BUTTON, xp, yp, press_limit)
+            .map(|(count, _)| (count * button.cost, vec![count])),
+        [button_a, button_b] => solve_pair(button_a, button_b, xp, yp, press_limit)
+            .map(|(a, b)| (a * button_a.cost + b * button_b.cost, vec![a, b])),
+        [rest @ .., last] => {
+            let max_by_x = (last.x > 0).then(|| xp / last.x as i128);
+            let max_by_y = (last.y > 0).then(|| yp / last.y as i128);
+            let max_by_limit = press_limit.map(|limit| limit as i128);
+            let max_count = [max_by_x, max_by_y, max_by_limit]
+                .into_iter()
+                .flatten()
+                .min()
+                .unwrap_or(0);
+            (0..=max_count)
+                .filter_map(|count| {
+                    let (spent, mut counts) = solve_buttons(
+                        rest,
+                        xp - count * last.x as i128,
+                        yp - count * last.y as i128,
+                        press_limit,
+                    )?;
+                    counts.push(count as usize);
+                    Some((spent + count as usize * last.cost, counts))
+                })
+                .min_by_key(|(spent, _)| *spent)
         }
     }
 }
 
-fn process(machines: &Vec<Machine>, prize_offset: usize) -> usize {
-    machines
+fn solve_min_spent(
+    machine: &Machine,
+    settings: &Settings,
+) -> Result<Option<Presses>, &'static str> {
+    if machine.buttons.len() > 2 && settings.press_limit.is_none() {
+        return Err("Machines with more than two buttons need a press limit");
+    }
+    let xp = machine.xp as i128 + settings.prize_offset as i128;
+    let yp = machine.yp as i128 + settings.prize_offset as i128;
+    Ok(
+        solve_buttons(&machine.buttons, xp, yp, settings.press_limit)
+            .map(|(spent, counts)| Presses { counts, spent }),
+    )
+}
+
+fn process(
+    machines: &[Machine],
+    settings: &Settings,
+) -> Result<(usize, Vec<Option<Presses>>), &'static str> {
+    let presses = machines
         .iter()
-        .filter_map(|machine| solve_min_spent(machine, prize_offset))
-        .sum()
+        .map(|machine| solve_min_spent(machine, settings))
+        .collect::<Result<Vec<Option<Presses>>, &'static str>>()?;
+    let spent = presses.iter().flatten().map(|presses| presses.spent).sum();
+    Ok((spent, presses))
+}
+
+fn print_presses(machines: &[Machine], presses: &[Option<Presses>]) {
+    for (i, (machine, machine_presses)) in machines.iter().zip(presses).enumerate() {
+        match machine_presses {
+            Some(Presses { counts, spent }) => {
+                let counts = machine
+                    .buttons
+                    .iter()
+                    .zip(counts)
+                    .map(|(button, count)| format!("{} pressed {}", button.label, count))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                println!("Machine {}: {}, {} token", i + 1, counts, spent)
+            }
+            None => println!("Machine {}: prize unreachable", i + 1),
+        }
+    }
 }
 
 pub fn run(mut args: impl Iterator<Item = String>) {
@@ -157,32 +307,174 @@ pub fn run(mut args: impl Iterator<Item = String>) {
     println!("Input file: {}", config.in_file);
 
     let raw_dataset = read_input_file(&config.in_file);
-    let machines = to_machines(&raw_dataset);
+    let machines = to_machines(&raw_dataset, &config.costs).unwrap_or_else(|err| {
+        eprintln!("Problem parsing machines: {}", err);
+        std::process::exit(1);
+    });
 
-    let spent_first = process(&machines, 0);
+    if config.prize_offset.is_some() || config.press_limit.is_some() {
+        let settings = Settings {
+            prize_offset: config.prize_offset.unwrap_or(0),
+            press_limit: config.press_limit,
+        };
+        let (spent, presses) = process(&machines, &settings).unwrap_or_else(|err| {
+            eprintln!("Problem solving machines: {}", err);
+            std::process::exit(1);
+        });
+        print_presses(&machines, &presses);
+        println!("{} token needed", spent);
+        return;
+    }
+
+    let settings_first = Settings {
+        prize_offset: 0,
+        press_limit: Some(PART_ONE_PRESS_LIMIT),
+    };
+    let (spent_first, presses_first) = process(&machines, &settings_first).unwrap_or_else(|err| {
+        eprintln!("Problem solving machines: {}", err);
+        std::process::exit(1);
+    });
+    print_presses(&machines, &presses_first);
     println!("{} token needed", spent_first);
 
-    let spent_second = process(&machines, 10000000000000);
-    println!("{} token needed if prize position offset by 10000000000000", spent_second);
+    let settings_second = Settings {
+        prize_offset: PART_TWO_PRIZE_OFFSET,
+        press_limit: None,
+    };
+    let (spent_second, presses_second) = match process(&machines, &settings_second) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("Skipping offset prize positions: {}", err);
+            return;
+        }
+    };
+    print_presses(&machines, &presses_second);
+    println!(
+        "{} token needed if prize position offset by {}",
+        spent_second, PART_TWO_PRIZE_OFFSET
+    );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn settings(prize_offset: usize, press_limit: Option<usize>) -> Settings {
+        Settings {
+            prize_offset,
+            press_limit,
+        }
+    }
+
+    fn puzzle_costs() -> HashMap<char, usize> {
+        HashMap::from([('A', 3), ('B', 1)])
+    }
+
     #[test]
     fn test_process_ex() {
         let raw_dataset = read_input_file("input/day13_ex.txt");
-        let machines = to_machines(&raw_dataset);
-        assert_eq!(process(&machines, 0), 480);
-        assert_eq!(process(&machines, 10000000000000), 875318608908);
+        let machines = to_machines(&raw_dataset, &puzzle_costs()).unwrap();
+        assert_eq!(process(&machines, &settings(0, Some(100))).unwrap().0, 480);
+        assert_eq!(
+            process(&machines, &settings(10000000000000, None))
+                .unwrap()
+                .0,
+            875318608908
+        );
+        let (_, presses) = process(&machines, &settings(0, Some(100))).unwrap();
+        assert_eq!(
+            presses[0].as_ref().map(|p| p.counts.clone()),
+            Some(vec![80, 40])
+        );
+        assert!(presses[1].is_none());
+    }
+
+    #[test]
+    fn test_solve_collinear() {
+        let machine = |xa, ya, xb, yb, xp, yp, cost_a| Machine {
+            buttons: vec![
+                Button {
+                    label: 'A',
+                    x: xa,
+                    y: ya,
+                    cost: cost_a,
+                },
+                Button {
+                    label: 'B',
+                    x: xb,
+                    y: yb,
+                    cost: 1,
+                },
+            ],
+            xp,
+            yp,
+        };
+        let spent = |machine: Machine, settings: &Settings| {
+            solve_min_spent(&machine, settings)
+                .unwrap()
+                .map(|presses| presses.spent)
+        };
+        let puzzle = settings(0, None);
+        assert_eq!(spent(machine(2, 2, 1, 1, 10, 10, 3), &puzzle), Some(10));
+        assert_eq!(spent(machine(4, 4, 1, 1, 10, 10, 3), &puzzle), Some(8));
+        assert_eq!(spent(machine(4, 6, 6, 9, 14, 21, 3), &puzzle), Some(7));
+        assert_eq!(spent(machine(2, 2, 1, 1, 10, 11, 3), &puzzle), None);
+        assert_eq!(spent(machine(2, 2, 4, 4, 5, 5, 3), &puzzle), None);
+        assert_eq!(spent(machine(0, 0, 3, 5, 9, 15, 3), &puzzle), Some(3));
+        assert_eq!(
+            spent(
+                machine(7, 7, 3, 3, 0, 0, 3),
+                &settings(10000000000000, None)
+            ),
+            Some(3333333333334)
+        );
+        let limited = settings(0, Some(1));
+        assert_eq!(spent(machine(4, 4, 1, 1, 10, 10, 3), &limited), None);
+        let limited = settings(0, Some(6));
+        assert_eq!(spent(machine(4, 4, 1, 1, 10, 10, 10), &limited), Some(16));
+    }
+
+    #[test]
+    fn test_solve_more_buttons() {
+        let raw_dataset =
+            "Button A: X+2, Y+1\nButton B: X+1, Y+2\nButton C: X+1, Y+1\nPrize: X=3, Y=3\n\n\
+            Button A: X+5, Y+0\nPrize: X=15, Y=0\n";
+        let mut costs = puzzle_costs();
+        assert!(to_machines(raw_dataset, &costs).is_err());
+        costs.insert('C', 1);
+        let machines = to_machines(raw_dataset, &costs).unwrap();
+        assert!(process(&machines, &settings(0, None)).is_err());
+        let (spent, presses) = process(&machines, &settings(0, Some(100))).unwrap();
+        assert_eq!(spent, 3 + 9);
+        assert_eq!(presses[0].as_ref().unwrap().counts, vec![0, 0, 3]);
+        assert_eq!(presses[1].as_ref().unwrap().counts, vec![3]);
+        costs.insert('C', 2);
+        let machines = to_machines(raw_dataset, &costs).unwrap();
+        let (_, presses) = process(&machines, &settings(0, Some(100))).unwrap();
+        assert_eq!(presses[0].as_ref().unwrap().counts, vec![1, 1, 0]);
+        assert!(process(&machines, &settings(0, Some(2))).unwrap().1[1].is_none());
+
+        // the press limit bounds the search even when the prize is offset far away
+        assert!(process(&machines, &settings(PART_TWO_PRIZE_OFFSET, None)).is_err());
+        let (spent, presses) =
+            process(&machines, &settings(PART_TWO_PRIZE_OFFSET, Some(100))).unwrap();
+        assert_eq!(spent, 0);
+        assert!(presses.iter().all(|presses| presses.is_none()));
     }
 
     #[test]
     fn test_process() {
         let raw_dataset = read_input_file("input/day13.txt");
-        let machines = to_machines(&raw_dataset);
-        assert_eq!(process(&machines, 0), 37686);
-        assert_eq!(process(&machines, 10000000000000), 77204516023437);
+        let machines = to_machines(&raw_dataset, &puzzle_costs()).unwrap();
+        assert_eq!(
+            process(&machines, &settings(0, Some(100))).unwrap().0,
+            37686
+        );
+        assert_eq!(
+            process(&machines, &settings(10000000000000, None))
+                .unwrap()
+                .0,
+            77204516023437
+        );
     }
 }
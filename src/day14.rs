@@ -1,17 +1,33 @@
+use rayon::prelude::*;
 use regex_lite::Regex;
-use std::collections::HashSet;
-use std::sync::atomic::{AtomicU16, Ordering};
-use std::{
-    fs,
-    sync::{atomic::AtomicU8, Arc},
-    thread,
-};
+use std::fs;
+use std::io::{BufWriter, Write};
 
 use crate::{day6::MapSize, day8::Position};
 
+const GIF_FRAME_DELAY: u16 = 50;
+
 struct Config {
     in_file: String,
-    map_size: MapSize,
+    width: Option<u8>,
+    height: Option<u8>,
+    frames: Option<(u16, u16)>,
+    out_file: Option<String>,
+    stats_at: u16,
+    areas: Vec<Area>,
+}
+
+fn to_size(value: &str) -> Result<u8, &'static str> {
+    match value.parse::<u8>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err("Map size must be a number between 1 and 255"),
+    }
+}
+
+fn to_seconds(value: &str) -> Result<u16, &'static str> {
+    value
+        .parse()
+        .map_err(|_| "Seconds must be a number between 0 and 65535")
 }
 
 impl Config {
@@ -20,23 +36,54 @@ impl Config {
             Some(arg) => arg,
             None => return Err("Missing input file argument"),
         };
-        let is_ex = match args.next() {
-            Some(_) => true,
-            None => false,
-        };
-
-        let map_size = match is_ex {
-            true => MapSize {
-                width: 11,
-                height: 7,
-            },
-            false => MapSize {
-                width: 101,
-                height: 103,
-            },
-        };
+        let mut width = None;
+        let mut height = None;
+        let mut frames = None;
+        let mut out_file = None;
+        let mut stats_at = 100;
+        let mut areas = Vec::new();
+        while let Some(option) = args.next() {
+            let value = args.next().ok_or("Missing option value")?;
+            match option.as_str() {
+                "--width" => width = Some(to_size(&value)?),
+                "--height" => height = Some(to_size(&value)?),
+                "--second" => {
+                    let seconds = to_seconds(&value)?;
+                    frames = Some((seconds, seconds));
+                }
+                "--frames" => {
+                    let (from, to) = value
+                        .split_once('-')
+                        .ok_or("Frames must be a range like 100-200")?;
+                    let (from, to) = (to_seconds(from)?, to_seconds(to)?);
+                    if from > to {
+                        return Err("Frames range must not be descending");
+                    }
+                    frames = Some((from, to));
+                }
+                "--out" => out_file = Some(value),
+                "--stats-at" => stats_at = to_seconds(&value)?,
+                "--area" => areas.push(Area::from_str(&value)?),
+                _ => {
+                    return Err(
+                        "Unknown option, expected --width, --height, --second, --frames, --out, --stats-at or --area",
+                    )
+                }
+            }
+        }
+        if out_file.is_some() && frames.is_none() {
+            return Err("--out requires --second or --frames");
+        }
 
-        Ok(Config { in_file, map_size })
+        Ok(Config {
+            in_file,
+            width,
+            height,
+            frames,
+            out_file,
+            stats_at,
+            areas,
+        })
     }
 }
 
@@ -60,22 +107,24 @@ impl<'a> Robot<'a> {
         vel_col: &str,
         vel_row: &str,
         map_size: &'a MapSize,
-    ) -> Robot<'a> {
-        let pos_row: u8 = pos_row.parse().unwrap();
-        let pos_col: u8 = pos_col.parse().unwrap();
-        let vel_row: i16 = vel_row.parse().unwrap();
-        let vel_col: i16 = vel_col.parse().unwrap();
-        let vel_row: u8 = if vel_row < 0 {
-            (map_size.height as i16 + vel_row) as u8
-        } else {
-            vel_row as u8
+    ) -> Result<Robot<'a>, &'static str> {
+        let pos_row: u8 = match pos_row.parse() {
+            Ok(row) if row < map_size.height => row,
+            _ => return Err("Robot position does not fit the map"),
         };
-        let vel_col: u8 = if vel_col < 0 {
-            (map_size.width as i16 + vel_col) as u8
-        } else {
-            vel_col as u8
+        let pos_col: u8 = match pos_col.parse() {
+            Ok(col) if col < map_size.width => col,
+            _ => return Err("Robot position does not fit the map"),
+        };
+        let to_velocity = |velocity: &str, size: u8| match velocity.parse::<i16>() {
+            Ok(velocity) if velocity.unsigned_abs() < size as u16 => {
+                Ok(velocity.rem_euclid(size as i16) as u8)
+            }
+            _ => Err("Robot velocity does not fit the map"),
         };
-        Robot {
+        let vel_row = to_velocity(vel_row, map_size.height)?;
+        let vel_col = to_velocity(vel_col, map_size.width)?;
+        Ok(Robot {
             initial_position: Position {
                 row: pos_row,
                 col: pos_col,
@@ -85,7 +134,7 @@ impl<'a> Robot<'a> {
                 col: vel_col,
             },
             map_size,
-        }
+        })
     }
 
     fn get_position_at_second(&self, seconds: u16) -> Position {
@@ -98,23 +147,34 @@ impl<'a> Robot<'a> {
             col: col as u8,
         }
     }
+}
 
-    fn get_quadrant_at_second(&self, seconds: &u8) -> Option<u8> {
-        let pos = self.get_position_at_second(*seconds as u16);
-        let middle_row = self.map_size.height / 2;
-        let middle_col = self.map_size.width / 2;
-        if pos.row == middle_row || pos.col == middle_col {
-            return None;
-        }
-        let is_left = if pos.col < middle_col { 0u8 } else { 2u8 };
-        let is_top = if pos.row < middle_row { 1u8 } else { 2u8 };
-        Some(is_left + is_top)
-    }
+fn robot_regex() -> Regex {
+    Regex::new(r"p=(?<pr>\d+),(?<pc>\d+) v=(?<vr>-?\d+),(?<vc>-?\d+)").unwrap()
+}
+
+fn detect_map_size(raw_dataset: &str) -> Result<MapSize, &'static str> {
+    let (max_col, max_row) = robot_regex().captures_iter(raw_dataset).try_fold(
+        (0u8, 0u8),
+        |(max_col, max_row), capture| {
+            let col = capture["pr"].parse::<u8>();
+            let row = capture["pc"].parse::<u8>();
+            match (col, row) {
+                (Ok(col), Ok(row)) if col < u8::MAX && row < u8::MAX => {
+                    Ok((max_col.max(col), max_row.max(row)))
+                }
+                _ => Err("Robot position is too large to detect map size"),
+            }
+        },
+    )?;
+    Ok(MapSize {
+        width: max_col + 1,
+        height: max_row + 1,
+    })
 }
 
-fn to_robots<'a>(raw_dataset: &str, map_size: &'a MapSize) -> Vec<Robot<'a>> {
-    let regex = Regex::new(r"p=(?<pr>\d+),(?<pc>\d+) v=(?<vr>-?\d+),(?<vc>-?\d+)").unwrap();
-    regex
+fn to_robots<'a>(raw_dataset: &str, map_size: &'a MapSize) -> Result<Vec<Robot<'a>>, &'static str> {
+    robot_regex()
         .captures_iter(raw_dataset)
         .map(|capture| {
             Robot::new(
@@ -128,70 +188,324 @@ fn to_robots<'a>(raw_dataset: &str, map_size: &'a MapSize) -> Vec<Robot<'a>> {
         .collect()
 }
 
-fn process_first(robots: &Vec<Robot>, seconds: u8) -> usize {
-    let q1 = Arc::new(AtomicU8::new(0));
-    let q2 = Arc::new(AtomicU8::new(0));
-    let q3 = Arc::new(AtomicU8::new(0));
-    let q4 = Arc::new(AtomicU8::new(0));
+struct Area {
+    col: u8,
+    row: u8,
+    width: u8,
+    height: u8,
+}
 
-    let thread_count = thread::available_parallelism().unwrap().get();
-    let per_thread = (robots.len() / thread_count) + 1;
-    thread::scope(|scope| {
-        let calc = |thread_i| {
-            let mut counts: [u8; 4] = [0, 0, 0, 0];
-            for quadrant in robots
-                .iter()
-                .skip(thread_i * per_thread)
-                .take(per_thread)
-                .filter_map(|robot| robot.get_quadrant_at_second(&seconds))
-            {
-                counts[quadrant as usize - 1] += 1;
-            }
-            if counts[0] > 0 {
-                q1.fetch_add(counts[0], Ordering::Relaxed);
-            }
-            if counts[1] > 0 {
-                q2.fetch_add(counts[1], Ordering::Relaxed);
-            }
-            if counts[2] > 0 {
-                q3.fetch_add(counts[2], Ordering::Relaxed);
-            }
-            if counts[3] > 0 {
-                q4.fetch_add(counts[3], Ordering::Relaxed);
+impl Area {
+    fn from_str(raw_area: &str) -> Result<Area, &'static str> {
+        let values = raw_area
+            .split(',')
+            .map(|v| v.parse::<u8>())
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| "Area values must be numbers between 0 and 255")?;
+        match values[..] {
+            [col, row, width, height] => Ok(Area {
+                col,
+                row,
+                width,
+                height,
+            }),
+            _ => Err("Area must be given as col,row,width,height"),
+        }
+    }
+}
+
+struct DensityGrid {
+    width: usize,
+    height: usize,
+    counts: Vec<usize>,
+    // (height + 1) * (width + 1) summed-area table, first row and column are zero
+    prefix_sums: Vec<usize>,
+}
+
+impl DensityGrid {
+    fn new(robots: &[Robot], map_size: &MapSize, seconds: u16) -> DensityGrid {
+        let width = map_size.width as usize;
+        let height = map_size.height as usize;
+        let counts = robots
+            .par_iter()
+            .fold(
+                || vec![0usize; width * height],
+                |mut counts, robot| {
+                    let position = robot.get_position_at_second(seconds);
+                    counts[position.row as usize * width + position.col as usize] += 1;
+                    counts
+                },
+            )
+            .reduce(
+                || vec![0usize; width * height],
+                |mut acc, counts| {
+                    acc.iter_mut().zip(counts).for_each(|(a, c)| *a += c);
+                    acc
+                },
+            );
+        let mut prefix_sums = vec![0usize; (width + 1) * (height + 1)];
+        for row in 0..height {
+            for col in 0..width {
+                prefix_sums[(row + 1) * (width + 1) + col + 1] = counts[row * width + col]
+                    + prefix_sums[row * (width + 1) + col + 1]
+                    + prefix_sums[(row + 1) * (width + 1) + col]
+                    - prefix_sums[row * (width + 1) + col];
             }
-        };
-        for i in 0..thread_count {
-            scope.spawn(move || calc(i));
         }
-    });
-    let q1 = q1.load(Ordering::Acquire) as usize;
-    let q2 = q2.load(Ordering::Acquire) as usize;
-    let q3 = q3.load(Ordering::Acquire) as usize;
-    let q4 = q4.load(Ordering::Acquire) as usize;
-    q1 * q2 * q3 * q4
+        DensityGrid {
+            width,
+            height,
+            counts,
+            prefix_sums,
+        }
+    }
+
+    fn get(&self, position: &Position) -> usize {
+        self.counts[position.row as usize * self.width + position.col as usize]
+    }
+
+    // area is clipped to the map
+    fn count_in(&self, area: &Area) -> usize {
+        let left = (area.col as usize).min(self.width);
+        let top = (area.row as usize).min(self.height);
+        let right = (area.col as usize + area.width as usize).min(self.width);
+        let bottom = (area.row as usize + area.height as usize).min(self.height);
+        let at = |row: usize, col: usize| self.prefix_sums[row * (self.width + 1) + col];
+        at(bottom, right) + at(top, left) - at(top, right) - at(bottom, left)
+    }
+
+    // top left, top right, bottom left, bottom right, robots on the middle lines are excluded
+    fn quadrant_counts(&self) -> [usize; 4] {
+        let middle_col = (self.width / 2) as u8;
+        let middle_row = (self.height / 2) as u8;
+        let right_col = self.width.div_ceil(2) as u8;
+        let bottom_row = self.height.div_ceil(2) as u8;
+        [
+            (0, 0, middle_col, middle_row),
+            (right_col, 0, middle_col, middle_row),
+            (0, bottom_row, middle_col, middle_row),
+            (right_col, bottom_row, middle_col, middle_row),
+        ]
+        .map(|(col, row, width, height)| {
+            self.count_in(&Area {
+                col,
+                row,
+                width,
+                height,
+            })
+        })
+    }
+
+    // None if the product does not fit in 128 bits
+    fn safety_factor(&self) -> Option<u128> {
+        self.quadrant_counts()
+            .iter()
+            .try_fold(1u128, |product, &count| product.checked_mul(count as u128))
+    }
 }
 
-fn process_second(robots: &Vec<Robot>) -> u16 {
-    use rayon::prelude::*;
-    let seconds = Arc::new(AtomicU16::new(0));
-    (0u16..)
+struct Candidate {
+    seconds: u16,
+    col_score: f64,
+    row_score: f64,
+    score: f64,
+}
+
+fn variance(values: impl Iterator<Item = u32>) -> f64 {
+    let (count, sum, square_sum) =
+        values.fold((0f64, 0f64, 0f64), |(count, sum, square_sum), v| {
+            (count + 1.0, sum + v as f64, square_sum + (v as f64).powi(2))
+        });
+    if count == 0.0 {
+        return 0.0;
+    }
+    square_sum / count - (sum / count).powi(2)
+}
+
+// variance of one axis for every phase within its period, normalized by the mean variance
+fn score_phases(robots: &[Robot], period: u8, axis: impl Fn(&Position) -> u8) -> Vec<(u8, f64)> {
+    let variances: Vec<f64> = (0..period)
+        .map(|phase| {
+            variance(
+                robots
+                    .iter()
+                    .map(|robot| axis(&robot.get_position_at_second(phase as u16)) as u32),
+            )
+        })
+        .collect();
+    let mean = variances.iter().sum::<f64>() / variances.len() as f64;
+    let mut phases: Vec<(u8, f64)> = variances
         .into_iter()
-        .par_bridge()
-        .into_par_iter()
-        .take_any_while(|_| seconds.load(Ordering::Relaxed) == 0)
-        .for_each(|thread_seconds| {
-            let mut positions: HashSet<Position> = HashSet::new();
-            for robot in robots.iter() {
-                if !positions.insert(robot.get_position_at_second(thread_seconds)) {
-                    return;
-                }
-            }
-            let old_seconds = seconds.load(Ordering::Relaxed);
-            if old_seconds == 0 || old_seconds > thread_seconds {
-                seconds.store(thread_seconds, Ordering::Relaxed);
+        .enumerate()
+        .map(|(phase, variance)| match mean > 0.0 {
+            true => (phase as u8, variance / mean),
+            false => (phase as u8, 1.0),
+        })
+        .collect();
+    phases.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    phases
+}
+
+// smallest t with t % width == col_phase and t % height == row_phase
+fn combine_phases(col_phase: u8, width: u8, row_phase: u8, height: u8) -> Option<u16> {
+    (0..height as u16)
+        .map(|k| col_phase as u16 + k * width as u16)
+        .find(|t| t % height as u16 == row_phase as u16)
+}
+
+fn find_easter_egg_candidates(robots: &[Robot], map_size: &MapSize, top: usize) -> Vec<Candidate> {
+    let col_phases = score_phases(robots, map_size.width, |position| position.col);
+    let row_phases = score_phases(robots, map_size.height, |position| position.row);
+    let mut candidates: Vec<Candidate> = col_phases
+        .iter()
+        .take(top)
+        .flat_map(|&(col_phase, col_score)| {
+            row_phases
+                .iter()
+                .take(top)
+                .filter_map(move |&(row_phase, row_score)| {
+                    let seconds =
+                        combine_phases(col_phase, map_size.width, row_phase, map_size.height)?;
+                    Some(Candidate {
+                        seconds,
+                        col_score,
+                        row_score,
+                        score: col_score + row_score,
+                    })
+                })
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.score.total_cmp(&b.score).then(a.seconds.cmp(&b.seconds)));
+    candidates
+}
+
+fn render_ascii(robots: &[Robot], map_size: &MapSize, seconds: u16) -> String {
+    let grid = DensityGrid::new(robots, map_size, seconds);
+    (0..map_size.height)
+        .map(|row| {
+            (0..map_size.width)
+                .map(|col| match grid.get(&Position { row, col }) {
+                    0 => '.',
+                    count if count < 10 => char::from_digit(count as u32, 10).unwrap(),
+                    _ => '+',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// one byte per cell, row by row, 1 when at least one robot is on it
+fn to_pixels(robots: &[Robot], map_size: &MapSize, seconds: u16) -> Vec<u8> {
+    DensityGrid::new(robots, map_size, seconds)
+        .counts
+        .into_iter()
+        .map(|count| count.min(1) as u8)
+        .collect()
+}
+
+fn write_pbm(out_file: &str, map_size: &MapSize, pixels: &[u8]) -> Result<(), &'static str> {
+    let rows = pixels
+        .chunks(map_size.width as usize)
+        .map(|row| {
+            row.iter()
+                .map(|pixel| pixel.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let pbm = format!("P1\n{} {}\n{}\n", map_size.width, map_size.height, rows);
+    fs::write(out_file, pbm).map_err(|_| "Problem writing PBM file")
+}
+
+fn write_png(out_file: &str, map_size: &MapSize, pixels: &[u8]) -> Result<(), &'static str> {
+    let file = fs::File::create(out_file).map_err(|_| "Problem creating PNG file")?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        map_size.width as u32,
+        map_size.height as u32,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let data: Vec<u8> = pixels.iter().map(|pixel| pixel * 255).collect();
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&data))
+        .map_err(|_| "Problem writing PNG file")
+}
+
+fn write_gif(out_file: &str, map_size: &MapSize, frames: &[Vec<u8>]) -> Result<(), &'static str> {
+    let file = fs::File::create(out_file).map_err(|_| "Problem creating GIF file")?;
+    let palette = [0, 0, 0, 0, 255, 0];
+    let (width, height) = (map_size.width as u16, map_size.height as u16);
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &palette)
+        .map_err(|_| "Problem writing GIF file")?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(|_| "Problem writing GIF file")?;
+    for pixels in frames {
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, pixels.clone(), None);
+        frame.delay = GIF_FRAME_DELAY;
+        encoder
+            .write_frame(&frame)
+            .map_err(|_| "Problem writing GIF file")?;
+    }
+    encoder
+        .into_inner()
+        .map_err(|_| "Problem writing GIF file")?
+        .flush()
+        .map_err(|_| "Problem writing GIF file")
+}
+
+fn numbered_file(out_file: &str, seconds: u16) -> String {
+    match out_file.rsplit_once('.') {
+        Some((name, extension)) => format!("{}_{}.{}", name, seconds, extension),
+        None => format!("{}_{}", out_file, seconds),
+    }
+}
+
+fn render(
+    robots: &[Robot],
+    map_size: &MapSize,
+    (from, to): (u16, u16),
+    out_file: Option<&str>,
+) -> Result<(), &'static str> {
+    let out_file = match out_file {
+        Some(out_file) => out_file,
+        None => {
+            for seconds in from..=to {
+                println!("After {} seconds:", seconds);
+                println!("{}", render_ascii(robots, map_size, seconds));
             }
-        });
-    seconds.load(Ordering::Acquire) as u16
+            return Ok(());
+        }
+    };
+    let extension = out_file.rsplit_once('.').map(|(_, extension)| extension);
+    if extension == Some("gif") {
+        let frames: Vec<Vec<u8>> = (from..=to)
+            .map(|seconds| to_pixels(robots, map_size, seconds))
+            .collect();
+        write_gif(out_file, map_size, &frames)?;
+        println!("Wrote {} frames to {}", frames.len(), out_file);
+        return Ok(());
+    }
+    for seconds in from..=to {
+        let frame_file = match from == to {
+            true => out_file.to_string(),
+            false => numbered_file(out_file, seconds),
+        };
+        let pixels = to_pixels(robots, map_size, seconds);
+        match extension {
+            Some("pbm") => write_pbm(&frame_file, map_size, &pixels)?,
+            Some("png") => write_png(&frame_file, map_size, &pixels)?,
+            Some("txt") => fs::write(&frame_file, render_ascii(robots, map_size, seconds) + "\n")
+                .map_err(|_| "Problem writing text file")?,
+            _ => return Err("Output file must end with .txt, .pbm, .png or .gif"),
+        }
+        println!("Wrote {}", frame_file);
+    }
+    Ok(())
 }
 
 pub fn run(mut args: impl Iterator<Item = String>) {
@@ -202,17 +516,67 @@ pub fn run(mut args: impl Iterator<Item = String>) {
     println!("Input file: {}", config.in_file);
 
     let raw_dataset = read_input_file(&config.in_file);
-    let robots = to_robots(&raw_dataset, &config.map_size);
-    let safety_factor_at_100_seconds = process_first(&robots, 100);
-    println!(
-        "Safety factor value at 100 seconds: {}",
-        safety_factor_at_100_seconds
-    );
-    let first_easter_egg_time = process_second(&robots);
+    let detected_size = detect_map_size(&raw_dataset).unwrap_or_else(|err| {
+        eprintln!("Problem detecting map size: {}", err);
+        std::process::exit(1);
+    });
+    let map_size = MapSize {
+        width: config.width.unwrap_or(detected_size.width),
+        height: config.height.unwrap_or(detected_size.height),
+    };
+    println!("Map size: {}x{}", map_size.width, map_size.height);
+    let robots = to_robots(&raw_dataset, &map_size).unwrap_or_else(|err| {
+        eprintln!("Problem parsing robots: {}", err);
+        std::process::exit(1);
+    });
+    if let Some(frames) = config.frames {
+        if let Err(err) = render(&robots, &map_size, frames, config.out_file.as_deref()) {
+            eprintln!("Problem rendering robots: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let grid = DensityGrid::new(&robots, &map_size, config.stats_at);
     println!(
-        "First time Easter egg is display at: {} seconds",
-        first_easter_egg_time
+        "Robots per quadrant at {} seconds: {:?}",
+        config.stats_at,
+        grid.quadrant_counts()
     );
+    for area in config.areas.iter() {
+        println!(
+            "Robots in area {},{} size {}x{} at {} seconds: {}",
+            area.col,
+            area.row,
+            area.width,
+            area.height,
+            config.stats_at,
+            grid.count_in(area)
+        );
+    }
+    match grid.safety_factor() {
+        Some(safety_factor) => println!(
+            "Safety factor value at {} seconds: {}",
+            config.stats_at, safety_factor
+        ),
+        None => println!(
+            "Safety factor value at {} seconds does not fit in 128 bits",
+            config.stats_at
+        ),
+    }
+    let candidates = find_easter_egg_candidates(&robots, &map_size, 3);
+    match candidates.first() {
+        Some(candidate) => println!(
+            "First time Easter egg is display at: {} seconds",
+            candidate.seconds
+        ),
+        None => println!("No Easter egg candidate found"),
+    }
+    for candidate in candidates.iter().take(5) {
+        println!(
+            "Candidate {} seconds, score {:.3} (column {:.3}, row {:.3})",
+            candidate.seconds, candidate.score, candidate.col_score, candidate.row_score
+        );
+    }
 }
 
 #[cfg(test)]
@@ -221,23 +585,120 @@ mod tests {
 
     #[test]
     fn test_ex() {
-        let mut args = vec!["input/day14_ex.txt".to_string(), "ex".to_string()].into_iter();
+        let mut args = vec![
+            "input/day14_ex.txt".to_string(),
+            "--width".to_string(),
+            "11".to_string(),
+            "--height".to_string(),
+            "7".to_string(),
+        ]
+        .into_iter();
         let config = Config::new(&mut args).unwrap();
         let raw_dataset = read_input_file(&config.in_file);
-        let robots = to_robots(&raw_dataset, &config.map_size);
-        let safety_factor_at_100_seconds = process_first(&robots, 100);
-        assert_eq!(safety_factor_at_100_seconds, 12);
+        let map_size = MapSize {
+            width: config.width.unwrap(),
+            height: config.height.unwrap(),
+        };
+        let robots = to_robots(&raw_dataset, &map_size).unwrap();
+        let safety_factor_at_100_seconds =
+            DensityGrid::new(&robots, &map_size, 100).safety_factor();
+        assert_eq!(safety_factor_at_100_seconds, Some(12));
+    }
+
+    #[test]
+    fn test_map_size() {
+        let raw_dataset = read_input_file("input/day14_ex.txt");
+        let map_size = detect_map_size(&raw_dataset).unwrap();
+        assert_eq!((map_size.width, map_size.height), (11, 7));
+        let small = MapSize {
+            width: 10,
+            height: 7,
+        };
+        assert!(to_robots(&raw_dataset, &small).is_err());
+        assert!(to_robots("p=1,1 v=-7,1", &map_size).is_ok());
+        assert!(to_robots("p=1,1 v=1,-7", &map_size).is_err());
+        assert!(to_robots("p=1,300 v=1,1", &map_size).is_err());
+        let mut args = vec!["x".to_string(), "--width".to_string(), "0".to_string()].into_iter();
+        assert!(Config::new(&mut args).is_err());
+    }
+
+    #[test]
+    fn test_density_grid() {
+        let raw_dataset = read_input_file("input/day14_ex.txt");
+        let map_size = detect_map_size(&raw_dataset).unwrap();
+        let robots = to_robots(&raw_dataset, &map_size).unwrap();
+        let grid = DensityGrid::new(&robots, &map_size, 100);
+        assert_eq!(grid.quadrant_counts(), [1, 3, 4, 1]);
+        assert_eq!(grid.get(&Position { row: 0, col: 6 }), 2);
+        let whole_map = Area::from_str("0,0,255,255").unwrap();
+        assert_eq!(grid.count_in(&whole_map), 12);
+        assert_eq!(grid.count_in(&Area::from_str("3,5,2,1").unwrap()), 3);
+        assert!(Area::from_str("1,2,3").is_err());
+
+        let many_robots: Vec<Robot> = (0..100_000)
+            .map(|_| Robot::new("0", "0", "1", "1", &map_size).unwrap())
+            .collect();
+        let grid = DensityGrid::new(&many_robots, &map_size, 1);
+        assert_eq!(grid.quadrant_counts(), [100_000, 0, 0, 0]);
+        assert_eq!(grid.safety_factor(), Some(0));
+
+        let many_robots: Vec<Robot> = [("0", "0"), ("10", "0"), ("0", "6"), ("10", "6")]
+            .iter()
+            .flat_map(|corner| std::iter::repeat_n(corner, 70_000))
+            .map(|&(col, row)| Robot::new(col, row, "0", "0", &map_size).unwrap())
+            .collect();
+        let grid = DensityGrid::new(&many_robots, &map_size, 100);
+        assert_eq!(grid.quadrant_counts(), [70_000; 4]);
+        assert_eq!(grid.safety_factor(), Some(70_000u128.pow(4)));
+    }
+
+    #[test]
+    fn test_render() {
+        let raw_dataset = read_input_file("input/day14_ex.txt");
+        let map_size = detect_map_size(&raw_dataset).unwrap();
+        let robots = to_robots(&raw_dataset, &map_size).unwrap();
+        assert_eq!(
+            render_ascii(&robots, &map_size, 0),
+            "1.12.......\n\
+             ...........\n\
+             ...........\n\
+             ......11.11\n\
+             1.1........\n\
+             .........1.\n\
+             .......1..."
+        );
+        let pixels = to_pixels(&robots, &map_size, 100);
+        assert_eq!(pixels.len(), 77);
+        assert_eq!(pixels.iter().filter(|&&pixel| pixel == 1).count(), 10);
+        assert_eq!(
+            render_ascii(&robots, &map_size, 100),
+            "......2..1.\n\
+             ...........\n\
+             1..........\n\
+             .11........\n\
+             .....1.....\n\
+             ...12......\n\
+             .1....1...."
+        );
+        assert_eq!(numbered_file("out/frame.png", 7), "out/frame_7.png");
     }
 
     #[test]
     fn test() {
-        let mut args = vec!["input/day14.txt".to_string()].into_iter();
-        let config = Config::new(&mut args).unwrap();
-        let raw_dataset = read_input_file(&config.in_file);
-        let robots = to_robots(&raw_dataset, &config.map_size);
-        let safety_factor_at_100_seconds = process_first(&robots, 100);
-        assert_eq!(safety_factor_at_100_seconds, 228457125);
-        let first_easter_egg_time = process_second(&robots);
-        assert_eq!(first_easter_egg_time, 6493);
+        let raw_dataset = read_input_file("input/day14.txt");
+        let map_size = detect_map_size(&raw_dataset).unwrap();
+        assert_eq!((map_size.width, map_size.height), (101, 103));
+        let robots = to_robots(&raw_dataset, &map_size).unwrap();
+        let safety_factor_at_100_seconds =
+            DensityGrid::new(&robots, &map_size, 100).safety_factor();
+        assert_eq!(safety_factor_at_100_seconds, Some(228457125));
+        let candidates = find_easter_egg_candidates(&robots, &map_size, 3);
+        assert_eq!(candidates.len(), 9);
+        assert_eq!(candidates[0].seconds, 6493);
+        assert!(candidates
+            .windows(2)
+            .all(|pair| pair[0].score <= pair[1].score));
+        assert_eq!(combine_phases(3, 101, 5, 103), Some(10305));
+        assert_eq!(combine_phases(1, 4, 2, 6), None);
     }
 }
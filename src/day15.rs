@@ -1,9 +1,45 @@
-use std::{collections::HashMap, fs};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    io::{self, BufRead, BufReader},
+};
 
 use crate::day8::Position;
 
 struct Config {
     in_file: String,
+    layout: Option<Layout>,
+    steps: Option<Vec<(usize, usize)>>,
+    trace_file: Option<String>,
+    explore: Option<String>,
+}
+
+fn to_step_ranges(raw_steps: &str) -> Result<Vec<(usize, usize)>, &'static str> {
+    raw_steps
+        .split(',')
+        .map(|raw_range| {
+            let (from, to) = raw_range.split_once('-').unwrap_or((raw_range, raw_range));
+            match (from.parse(), to.parse()) {
+                (Ok(from), Ok(to)) if from <= to => Ok((from, to)),
+                _ => Err("Steps must be a list like 0,5,10-20"),
+            }
+        })
+        .collect()
+}
+
+fn to_box_shape(raw_shape: &str) -> Result<Vec<(u8, u8)>, &'static str> {
+    raw_shape
+        .split(';')
+        .map(|raw_cell| {
+            let (row, col) = raw_cell
+                .split_once(',')
+                .ok_or("Shape cell must be row,col")?;
+            match (row.parse(), col.parse()) {
+                (Ok(row), Ok(col)) => Ok((row, col)),
+                _ => Err("Shape must be a list like 0,0;0,1;1,0"),
+            }
+        })
+        .collect()
 }
 
 impl Config {
@@ -12,256 +48,467 @@ impl Config {
             Some(arg) => arg,
             None => return Err("Missing input file argument"),
         };
+        let mut scale = None;
+        let mut box_shape = None;
+        let mut steps = None;
+        let mut trace_file = None;
+        let mut explore = None;
+        while let Some(option) = args.next() {
+            let value = args.next().ok_or("Missing option value")?;
+            match option.as_str() {
+                "--part" => {
+                    scale = match value.as_str() {
+                        "1" => Some(1),
+                        "2" => Some(2),
+                        _ => return Err("Part must be 1 or 2"),
+                    }
+                }
+                "--scale" => match value.parse::<u8>() {
+                    Ok(value) if value > 0 => scale = Some(value),
+                    _ => return Err("Scale must be a number between 1 and 255"),
+                },
+                "--shape" => box_shape = Some(to_box_shape(&value)?),
+                "--steps" => steps = Some(to_step_ranges(&value)?),
+                "--trace" => trace_file = Some(value),
+                "--explore" => explore = Some(value),
+                _ => return Err(
+                    "Unknown option, expected --part, --scale, --shape, --steps, --trace or --explore",
+                ),
+            }
+        }
+        let layout = match (scale, box_shape) {
+            (None, None) => None,
+            (scale, None) => Some(Layout::with_width(scale.unwrap_or(1))),
+            (scale, Some(box_shape)) => Some(Layout {
+                scale: scale.unwrap_or(1),
+                box_shape,
+            }),
+        };
 
-        Ok(Config { in_file })
+        Ok(Config {
+            in_file,
+            layout,
+            steps,
+            trace_file,
+            explore,
+        })
     }
 }
 
-impl Position {
-    fn next_position(&self, direction: &char) -> Position {
-        match direction {
-            '>' => Position {
-                row: self.row,
-                col: self.col + 1,
-            },
-            '^' => Position {
-                row: self.row - 1,
-                col: self.col,
-            },
-            'v' => Position {
-                row: self.row + 1,
-                col: self.col,
-            },
-            '<' => Position {
-                row: self.row,
-                col: self.col - 1,
-            },
-            _ => panic!("Invalid direction"),
+// every map tile is stretched `scale` columns wide, each `O` becomes a box of `box_shape`
+// cells given as (row, col) offsets from the stretched tile
+struct Layout {
+    scale: u8,
+    box_shape: Vec<(u8, u8)>,
+}
+
+impl Layout {
+    fn with_width(width: u8) -> Layout {
+        Layout {
+            scale: width,
+            box_shape: (0..width).map(|col| (0, col)).collect(),
         }
     }
 }
 
-struct Map {
-    robot: Position,
-    map: HashMap<Position, bool>,
+struct ReplayStep {
+    index: usize,
+    direction: char,
+    blocked: bool,
+    state: String,
 }
 
-impl Map {
-    fn new(raw_map_dataset: &str) -> Self {
-        let size = raw_map_dataset.find("\n").unwrap() + 1;
-        let robot_offset = raw_map_dataset.find("@").unwrap();
-        let robot = Position {
-            col: (robot_offset % size) as u8,
-            row: (robot_offset / size) as u8,
+struct Replay<'a> {
+    map: &'a mut Map,
+    instructions: std::iter::Enumerate<std::str::Chars<'a>>,
+}
+
+impl<'a> Replay<'a> {
+    fn new(map: &'a mut Map, instructions: &'a str) -> Self {
+        Replay {
+            map,
+            instructions: instructions.chars().enumerate(),
+        }
+    }
+
+    // moves without rendering, returns the 1-based move index, direction and whether it was blocked
+    fn step(&mut self) -> Option<(usize, char, bool)> {
+        let (i, direction) = self.instructions.next()?;
+        let moved = self.map.move_robot(&direction);
+        Some((i + 1, direction, !moved))
+    }
+}
+
+impl Iterator for Replay<'_> {
+    type Item = ReplayStep;
+
+    fn next(&mut self) -> Option<ReplayStep> {
+        let (index, direction, blocked) = self.step()?;
+        Some(ReplayStep {
+            index,
+            direction,
+            blocked,
+            state: self.map.render(),
+        })
+    }
+}
+
+impl Position {
+    fn next_position(&self, direction: &char) -> Option<Position> {
+        let (row, col) = match direction {
+            '>' => (Some(self.row), self.col.checked_add(1)),
+            '^' => (self.row.checked_sub(1), Some(self.col)),
+            'v' => (self.row.checked_add(1), Some(self.col)),
+            '<' => (Some(self.row), self.col.checked_sub(1)),
+            _ => return None,
         };
-        let lines = raw_map_dataset.lines();
-
-        let map: HashMap<Position, bool> = lines
-            .enumerate()
-            .flat_map(|(row, line)| {
-                line.chars().enumerate().map(move |(col, c)| {
-                    let position = Position {
-                        row: row as u8,
-                        col: col as u8,
-                    };
-                    (position, c)
-                })
-            })
-            .filter_map(|(position, c)| match c {
-                '#' => Some((position, false)),
-                'O' => Some((position, true)),
-                _ => None,
-            })
-            .fold(HashMap::new(), |mut acc, (position, movable)| {
-                acc.insert(position, movable);
-                acc
-            });
+        Some(Position {
+            row: row?,
+            col: col?,
+        })
+    }
+}
 
-        Map { robot, map }
-    }
-
-    fn clear_position(&mut self, position: &Position, direction: &char) -> bool {
-        let current = self.map.get(position);
-        match current {
-            None => true,
-            Some(&false) => false,
-            Some(&true) => {
-                let next_position = match direction {
-                    '>' => Position {
-                        row: position.row,
-                        col: position.col + 1,
-                    },
-                    '^' => Position {
-                        row: position.row - 1,
-                        col: position.col,
-                    },
-                    'v' => Position {
-                        row: position.row + 1,
-                        col: position.col,
-                    },
-                    '<' => Position {
-                        row: position.row,
-                        col: position.col - 1,
-                    },
-                    _ => panic!("Invalid direction"),
-                };
-                if self.clear_position(&next_position, direction) {
-                    // self.map.insert(*position, true);
-                    self.map.insert(next_position, true);
-                    self.map.remove(position);
-                    true
-                } else {
-                    false
-                }
+#[derive(Debug)]
+enum InputError {
+    Map(&'static str),
+    Tile { row: usize, col: usize, found: char },
+    Instruction { index: usize, found: char },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Map(message) => write!(f, "{}", message),
+            InputError::Tile { row, col, found } => {
+                write!(f, "Unknown tile '{}' at row {}, col {}", found, row, col)
+            }
+            InputError::Instruction { index, found } => {
+                write!(f, "Invalid instruction '{}' at index {}", found, index)
             }
         }
     }
+}
 
-    fn move_robot(&mut self, direction: &char) {
-        let next_position = self.robot.next_position(direction);
-        if self.clear_position(&next_position, direction) {
-            self.robot = next_position;
-        }
+fn split_input(raw_dataset: &str) -> Result<(&str, String), InputError> {
+    let (raw_map_dataset, raw_instructions) = raw_dataset.split_once("\n\n").ok_or(
+        InputError::Map("Input must be a map and instructions separated by a blank line"),
+    )?;
+    let instructions = raw_instructions.replace('\n', "");
+    if let Some((index, found)) = instructions
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !matches!(c, '^' | 'v' | '<' | '>'))
+    {
+        return Err(InputError::Instruction { index, found });
     }
+    Ok((raw_map_dataset, instructions))
+}
 
-    fn get_box_sum_coordinate(&self) -> u32 {
-        self.map
-            .iter()
-            .filter_map(|(position, movable)| match movable {
-                true => Some((position.row as u32 * 100) + position.col as u32),
-                false => None,
-            })
-            .sum()
+// a recorded move, `pushed` lists the moved boxes or is None when the move was blocked
+struct Move {
+    direction: char,
+    pushed: Option<Vec<usize>>,
+}
+
+fn get_opposite_direction(direction: &char) -> char {
+    match direction {
+        '>' => '<',
+        '<' => '>',
+        '^' => 'v',
+        'v' => '^',
+        _ => *direction,
     }
 }
 
-struct MapDoubleWide {
+struct Map {
     robot: Position,
-    map: HashMap<Position, Option<bool>>,
+    walls: HashSet<Position>,
+    boxes: Vec<Vec<Position>>,
+    occupied: HashMap<Position, usize>,
+    width: u8,
+    height: u8,
+    history: Vec<Move>,
+    undone: Vec<Move>,
 }
 
-impl MapDoubleWide {
-    fn new(raw_map_dataset: &str) -> Self {
-        let size = raw_map_dataset.find("\n").unwrap() + 1;
-        let robot_offset = raw_map_dataset.find("@").unwrap();
-        let robot = Position {
-            col: (robot_offset % size) as u8 * 2,
-            row: (robot_offset / size) as u8,
+fn add_box(
+    boxes: &mut Vec<Vec<Position>>,
+    occupied: &mut HashMap<Position, usize>,
+    cells: Vec<Position>,
+) -> Result<(), InputError> {
+    for cell in cells.iter() {
+        if occupied.insert(*cell, boxes.len()).is_some() {
+            return Err(InputError::Map("Boxes overlap each other"));
+        }
+    }
+    boxes.push(cells);
+    Ok(())
+}
+
+impl Map {
+    // moves past the map edge are blocked, so the border does not need to be walled
+    fn new(raw_map_dataset: &str, layout: &Layout) -> Result<Self, InputError> {
+        let lines: Vec<&str> = raw_map_dataset.lines().collect();
+        let width = lines.first().map_or(0, |line| line.chars().count());
+        if lines.iter().any(|line| line.chars().count() != width) {
+            return Err(InputError::Map("Map rows must all have the same width"));
+        }
+        let (width, height) = match (
+            u8::try_from(width * layout.scale as usize),
+            u8::try_from(lines.len()),
+        ) {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => return Err(InputError::Map("Map is too large")),
         };
+        if layout.box_shape.is_empty() {
+            return Err(InputError::Map("Box shape must have at least one cell"));
+        }
 
-        let lines = raw_map_dataset.lines();
-
-        let map = lines
-            .enumerate()
-            .flat_map(|(row, line)| {
-                line.chars().enumerate().map(move |(col, c)| {
-                    let position = Position {
-                        row: row as u8,
-                        col: col as u8 * 2,
-                    };
-                    (position, c)
-                })
-            })
-            .filter_map(|(position, c)| match c {
-                '#' => Some((position, false)),
-                'O' => Some((position, true)),
-                _ => None,
-            })
-            .fold(HashMap::new(), |mut acc, (position, movable)| {
-                let position_on_right = Position {
-                    row: position.row,
-                    col: position.col + 1,
-                };
-                let movable_option = match movable {
-                    true => Some(true),
-                    false => None,
-                };
-                let moveable_on_right_option = match movable {
-                    true => Some(false),
-                    false => None,
+        let mut robot = None;
+        let mut walls = HashSet::new();
+        let mut boxes = Vec::new();
+        let mut occupied = HashMap::new();
+        let mut wide_box: Option<Vec<Position>> = None;
+        for (row, line) in lines.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                let tile = Position {
+                    row: row as u8,
+                    col: col as u8 * layout.scale,
                 };
-                acc.insert(position, movable_option);
-                acc.insert(position_on_right, moveable_on_right_option);
-                acc
-            });
+                if wide_box.is_some() && !matches!(c, '=' | ']') {
+                    return Err(InputError::Map("Wide box must be closed by ']'"));
+                }
+                match c {
+                    '#' => walls.extend((0..layout.scale).map(|offset| Position {
+                        row: tile.row,
+                        col: tile.col + offset,
+                    })),
+                    'O' => {
+                        let cells = layout
+                            .box_shape
+                            .iter()
+                            .map(|(row_offset, col_offset)| {
+                                match (
+                                    tile.row.checked_add(*row_offset),
+                                    tile.col.checked_add(*col_offset),
+                                ) {
+                                    (Some(row), Some(col)) if row < height && col < width => {
+                                        Ok(Position { row, col })
+                                    }
+                                    _ => Err(InputError::Map("Box shape extends outside the map")),
+                                }
+                            })
+                            .collect::<Result<Vec<Position>, InputError>>()?;
+                        add_box(&mut boxes, &mut occupied, cells)?;
+                    }
+                    // saved maps write each box as laid out, a wide box reads `[`, `=`... `]`
+                    '[' | '=' | ']' if layout.scale != 1 => {
+                        return Err(InputError::Map("Wide boxes can only be read at scale 1"))
+                    }
+                    '[' if wide_box.is_none() => wide_box = Some(vec![tile]),
+                    '=' if wide_box.is_some() => wide_box.as_mut().unwrap().push(tile),
+                    ']' if wide_box.is_some() => {
+                        let mut cells = wide_box.take().unwrap();
+                        cells.push(tile);
+                        add_box(&mut boxes, &mut occupied, cells)?;
+                    }
+                    '@' if robot.is_some() => {
+                        return Err(InputError::Map("Map must contain exactly one robot"))
+                    }
+                    '@' => robot = Some(tile),
+                    '.' => {}
+                    found => return Err(InputError::Tile { row, col, found }),
+                }
+            }
+            if wide_box.is_some() {
+                return Err(InputError::Map("Wide box must be closed by ']'"));
+            }
+        }
+        let robot = robot.ok_or(InputError::Map("Map must contain exactly one robot"))?;
+        if occupied.contains_key(&robot) || occupied.keys().any(|cell| walls.contains(cell)) {
+            return Err(InputError::Map("Boxes overlap a wall or the robot"));
+        }
+
+        Ok(Map {
+            robot,
+            walls,
+            boxes,
+            occupied,
+            width,
+            height,
+            history: Vec::new(),
+            undone: Vec::new(),
+        })
+    }
 
-        MapDoubleWide { robot, map }
+    fn next_position(&self, position: &Position, direction: &char) -> Option<Position> {
+        position
+            .next_position(direction)
+            .filter(|next| next.row < self.height && next.col < self.width)
     }
 
-    fn can_move(&mut self, position: &Position, direction: &char) -> bool {
-        let current_position_data = self.map.get(position);
-        let another_position_of_box = match current_position_data {
-            None => {
-                return true;
+    // all boxes touched by the push, transitively, or None when any of them is blocked
+    fn get_pushed_boxes(&self, position: &Position, direction: &char) -> Option<Vec<usize>> {
+        let mut pushed = Vec::new();
+        let mut seen = HashSet::new();
+        let mut frontier = vec![*position];
+        while let Some(position) = frontier.pop() {
+            if self.walls.contains(&position) {
+                return None;
             }
-            Some(movable) => match movable {
-                None => {
-                    return false;
-                }
-                Some(true) => Position {
-                    row: position.row,
-                    col: position.col + 1,
-                },
-                Some(false) => Position {
-                    row: position.row,
-                    col: position.col - 1,
-                },
-            },
-        };
-        let next_position = position.next_position(direction);
-        let next_another_position_of_box = another_position_of_box.next_position(direction);
-        let self_can_move = self.can_move(&next_position, direction);
-        let another_can_move = next_another_position_of_box == *position
-            || self.can_move(&next_another_position_of_box, direction);
-        self_can_move && another_can_move
-    }
-
-    fn clear_position(&mut self, position: &Position, direction: &char) {
-        let current_position_data = self.map.get(position);
-        let another_position_of_box = match current_position_data {
-            None => {
-                return;
+            let id = match self.occupied.get(&position) {
+                Some(&id) => id,
+                None => continue,
+            };
+            if !seen.insert(id) {
+                continue;
             }
-            Some(movable) => match movable {
-                None => {
-                    return;
+            pushed.push(id);
+            for cell in self.boxes[id].iter() {
+                let next = self.next_position(cell, direction)?;
+                if self.occupied.get(&next) != Some(&id) {
+                    frontier.push(next);
                 }
-                Some(true) => Position {
-                    row: position.row,
-                    col: position.col + 1,
-                },
-                Some(false) => Position {
+            }
+        }
+        Some(pushed)
+    }
+
+    fn shift_boxes(&mut self, ids: &[usize], direction: &char) {
+        for id in ids.iter() {
+            for cell in self.boxes[*id].iter() {
+                self.occupied.remove(cell);
+            }
+        }
+        for id in ids.iter() {
+            let cells: Vec<Position> = self.boxes[*id]
+                .iter()
+                .filter_map(|cell| cell.next_position(direction))
+                .collect();
+            self.occupied.extend(cells.iter().map(|cell| (*cell, *id)));
+            self.boxes[*id] = cells;
+        }
+    }
+
+    fn apply_move(&mut self, direction: &char) -> Move {
+        let pushed = self
+            .next_position(&self.robot, direction)
+            .and_then(|next_position| {
+                let pushed = self.get_pushed_boxes(&next_position, direction)?;
+                self.shift_boxes(&pushed, direction);
+                self.robot = next_position;
+                Some(pushed)
+            });
+        Move {
+            direction: *direction,
+            pushed,
+        }
+    }
+
+    fn move_robot(&mut self, direction: &char) -> bool {
+        let applied = self.apply_move(direction);
+        let moved = applied.pushed.is_some();
+        self.history.push(applied);
+        self.undone.clear();
+        moved
+    }
+
+    fn undo(&mut self) -> bool {
+        let applied = match self.history.pop() {
+            Some(applied) => applied,
+            None => return false,
+        };
+        if let Some(pushed) = applied.pushed.as_deref() {
+            let opposite = get_opposite_direction(&applied.direction);
+            self.shift_boxes(pushed, &opposite);
+            self.robot = self.robot.next_position(&opposite).unwrap();
+        }
+        self.undone.push(applied);
+        true
+    }
+
+    fn redo(&mut self) -> bool {
+        let direction = match self.undone.pop() {
+            Some(applied) => applied.direction,
+            None => return false,
+        };
+        let applied = self.apply_move(&direction);
+        self.history.push(applied);
+        true
+    }
+
+    // every move since the map was parsed, blocked ones included
+    fn get_instructions(&self) -> String {
+        self.history
+            .iter()
+            .map(|applied| applied.direction)
+            .collect()
+    }
+
+    // boxes spanning several rows or with gaps render ambiguously, so they cannot be read back
+    fn is_saveable(&self) -> bool {
+        self.boxes.iter().all(|cells| {
+            let row = cells[0].row;
+            let min_col = cells.iter().map(|cell| cell.col).min().unwrap();
+            let max_col = cells.iter().map(|cell| cell.col).max().unwrap();
+            cells.iter().all(|cell| cell.row == row)
+                && (max_col - min_col) as usize + 1 == cells.len()
+        })
+    }
+
+    fn render_box_cell(&self, id: usize, position: &Position) -> char {
+        let cells = &self.boxes[id];
+        if cells.len() == 1 {
+            return 'O';
+        }
+        let is_same_box = |col: Option<u8>| {
+            col.is_some_and(|col| {
+                self.occupied.get(&Position {
                     row: position.row,
-                    col: position.col - 1,
-                },
-            },
+                    col,
+                }) == Some(&id)
+            })
         };
-        let current_position_data = self.map.remove(position).unwrap();
-        let another_position_of_box_data = self.map.remove(&another_position_of_box).unwrap();
-        let next_position = position.next_position(direction);
-        let next_another_position_of_box = another_position_of_box.next_position(direction);
-        self.clear_position(&next_position, direction);
-        self.clear_position(&next_another_position_of_box, direction);
-        self.map.insert(next_position, current_position_data);
-        self.map
-            .insert(next_another_position_of_box, another_position_of_box_data);
-    }
-
-    fn move_robot(&mut self, direction: &char) {
-        let next_position = self.robot.next_position(direction);
-        if self.can_move(&next_position, direction) {
-            self.clear_position(&next_position, direction);
-            self.robot = next_position;
+        match (
+            is_same_box(position.col.checked_sub(1)),
+            is_same_box(position.col.checked_add(1)),
+        ) {
+            (false, true) => '[',
+            (true, false) => ']',
+            (true, true) => '=',
+            (false, false) => '|',
         }
     }
 
+    fn render(&self) -> String {
+        (0..self.height)
+            .map(|row| {
+                (0..self.width)
+                    .map(|col| {
+                        let position = Position { row, col };
+                        if position == self.robot {
+                            '@'
+                        } else if self.walls.contains(&position) {
+                            '#'
+                        } else if let Some(&id) = self.occupied.get(&position) {
+                            self.render_box_cell(id, &position)
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // distance is measured from the top left cell of each box
     fn get_box_sum_coordinate(&self) -> u32 {
-        self.map
+        self.boxes
             .iter()
-            .filter_map(|(position, is_left)| match is_left {
-                Some(true) => Some((position.row as u32 * 100) + position.col as u32),
-                _ => None,
-            })
+            .filter_map(|cells| cells.iter().min_by_key(|cell| (cell.row, cell.col)))
+            .map(|position| (position.row as u32 * 100) + position.col as u32)
             .sum()
     }
 }
@@ -273,18 +520,159 @@ fn read_input_file(file_path: &str) -> String {
     })
 }
 
-fn process_first(map: &mut Map, instructions: &str) -> u32 {
+fn process(map: &mut Map, instructions: &str) -> u32 {
     for instruction in instructions.chars() {
         map.move_robot(&instruction);
     }
     map.get_box_sum_coordinate()
 }
 
-fn process_second(map: &mut MapDoubleWide, instructions: &str) -> u32 {
-    for instruction in instructions.chars() {
-        map.move_robot(&instruction);
+fn format_step(index: usize, direction: char, blocked: bool, state: &str) -> String {
+    let status = match blocked {
+        true => " blocked",
+        false => "",
+    };
+    format!("Move {} {}{}:\n{}\n", index, direction, status, state)
+}
+
+fn replay_warehouse(
+    map: &mut Map,
+    instructions: &str,
+    steps: Option<&[(usize, usize)]>,
+    trace_file: Option<&str>,
+) -> Result<(), &'static str> {
+    let is_selected = |index: usize| {
+        steps.is_some_and(|steps| {
+            steps
+                .iter()
+                .any(|(from, to)| (*from..=*to).contains(&index))
+        })
+    };
+    if is_selected(0) {
+        println!("Initial state:\n{}\n", map.render());
     }
-    map.get_box_sum_coordinate()
+    let mut trace = trace_file.map(|_| format!("Initial state:\n{}\n", map.render()));
+    let mut blocked_count = 0;
+    let mut replay = Replay::new(map, instructions);
+    if let Some(trace) = trace.as_mut() {
+        for step in replay.by_ref() {
+            let text = format_step(step.index, step.direction, step.blocked, &step.state);
+            if is_selected(step.index) {
+                println!("{}", text);
+            }
+            blocked_count += step.blocked as usize;
+            trace.push_str(&text);
+        }
+    } else {
+        while let Some((index, direction, blocked)) = replay.step() {
+            if is_selected(index) {
+                println!(
+                    "{}",
+                    format_step(index, direction, blocked, &replay.map.render())
+                );
+            }
+            blocked_count += blocked as usize;
+        }
+    }
+    if let (Some(trace_file), Some(trace)) = (trace_file, trace) {
+        fs::write(trace_file, trace).map_err(|_| "Problem writing trace file")?;
+        println!("Trace written to {}", trace_file);
+    }
+    println!("Blocked moves: {}", blocked_count);
+    println!(
+        "Sum of all boxes final coordinates: {}",
+        replay.map.get_box_sum_coordinate()
+    );
+    Ok(())
+}
+
+const EXPLORE_HELP: &str = "Commands: <moves> (e.g. <<^v>), undo/u [n], redo/r [n], map/m, \
+instructions/i [file], save file, quit/q, help/h";
+
+fn to_count(raw_count: Option<&str>) -> Result<usize, &'static str> {
+    raw_count.map_or(Ok(1), |raw_count| {
+        raw_count.parse().map_err(|_| "Count must be a number")
+    })
+}
+
+impl Map {
+    // runs one explore command, None ends the session
+    fn execute(&mut self, command: &str) -> Result<Option<String>, &'static str> {
+        let mut words = command.split_whitespace();
+        let message = match words.next() {
+            None => String::new(),
+            Some("undo" | "u") => {
+                let count = to_count(words.next())?;
+                let undone = (0..count).take_while(|_| self.undo()).count();
+                format!("Undone {} moves\n{}", undone, self.render())
+            }
+            Some("redo" | "r") => {
+                let count = to_count(words.next())?;
+                let redone = (0..count).take_while(|_| self.redo()).count();
+                format!("Redone {} moves\n{}", redone, self.render())
+            }
+            Some("map" | "m") => self.render(),
+            Some("instructions" | "i") => match words.next() {
+                Some(file) => {
+                    fs::write(file, self.get_instructions())
+                        .map_err(|_| "Problem writing instructions file")?;
+                    format!("Instructions written to {}", file)
+                }
+                None => self.get_instructions(),
+            },
+            Some("save") => match words.next() {
+                Some(_) if !self.is_saveable() => {
+                    return Err("Only maps whose boxes each fit in one row can be saved")
+                }
+                Some(file) => {
+                    fs::write(file, self.render() + "\n")
+                        .map_err(|_| "Problem writing map file")?;
+                    format!("Map written to {}", file)
+                }
+                None => return Err("Missing map file"),
+            },
+            Some("quit" | "q") => return Ok(None),
+            Some("help" | "h") => EXPLORE_HELP.to_string(),
+            Some(moves) => {
+                if !moves.chars().all(|c| matches!(c, '^' | 'v' | '<' | '>')) {
+                    return Err("Unknown command, type help for the list of commands");
+                }
+                let blocked = moves.chars().filter(|c| !self.move_robot(c)).count();
+                format!("Blocked moves: {}\n{}", blocked, self.render())
+            }
+        };
+        Ok(Some(message))
+    }
+
+    fn session(&mut self, input: impl BufRead, echo: bool) {
+        println!("{}", self.render());
+        for line in input.lines() {
+            let command = line.unwrap_or_else(|err| {
+                eprintln!("Problem reading command: {}", err);
+                std::process::exit(1);
+            });
+            if echo {
+                println!("> {}", command);
+            }
+            match self.execute(&command) {
+                Ok(Some(message)) if message.is_empty() => {}
+                Ok(Some(message)) => println!("{}", message),
+                Ok(None) => break,
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        println!(
+            "Sum of all boxes coordinates: {}",
+            self.get_box_sum_coordinate()
+        );
+    }
+}
+
+fn to_map(raw_map_dataset: &str, layout: &Layout) -> Map {
+    Map::new(raw_map_dataset, layout).unwrap_or_else(|err| {
+        eprintln!("Problem parsing map: {}", err);
+        std::process::exit(1);
+    })
 }
 
 pub fn run(mut args: impl Iterator<Item = String>) {
@@ -295,16 +683,49 @@ pub fn run(mut args: impl Iterator<Item = String>) {
     println!("Input file: {}", config.in_file);
 
     let raw_dataset = read_input_file(&config.in_file);
-    let mut raw_dataset_split = raw_dataset.split("\n\n");
-    let raw_map_dataset = raw_dataset_split.next().unwrap();
-    let instructions = raw_dataset_split.next().unwrap().replace("\n", "");
+    let (raw_map_dataset, instructions) = split_input(&raw_dataset).unwrap_or_else(|err| {
+        eprintln!("Problem parsing input: {}", err);
+        std::process::exit(1);
+    });
+
+    if let Some(script_file) = config.explore {
+        let layout = config.layout.unwrap_or(Layout::with_width(1));
+        let mut map = to_map(raw_map_dataset, &layout);
+        match script_file.as_str() {
+            "-" => map.session(io::stdin().lock(), false),
+            _ => {
+                let script = fs::File::open(&script_file).unwrap_or_else(|err| {
+                    eprintln!("Problem reading script file: {}", err);
+                    std::process::exit(1);
+                });
+                map.session(BufReader::new(script), true);
+            }
+        }
+        return;
+    }
+
+    if config.layout.is_some() || config.steps.is_some() || config.trace_file.is_some() {
+        let layout = config.layout.unwrap_or(Layout::with_width(1));
+        let mut map = to_map(raw_map_dataset, &layout);
+        let replayed = replay_warehouse(
+            &mut map,
+            &instructions,
+            config.steps.as_deref(),
+            config.trace_file.as_deref(),
+        );
+        if let Err(err) = replayed {
+            eprintln!("Problem replaying moves: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    let mut map = Map::new(raw_map_dataset);
-    let box_coor_sum = process_first(&mut map, &instructions);
+    let mut map = to_map(raw_map_dataset, &Layout::with_width(1));
+    let box_coor_sum = process(&mut map, &instructions);
     println!("Sum of all boxes final coordinates: {}", box_coor_sum);
 
-    let mut map_double_wide = MapDoubleWide::new(raw_map_dataset);
-    let box_coor_sum_double_wide = process_second(&mut map_double_wide, &instructions);
+    let mut map_double_wide = to_map(raw_map_dataset, &Layout::with_width(2));
+    let box_coor_sum_double_wide = process(&mut map_double_wide, &instructions);
     println!(
         "Sum of all boxes final coordinates in second warehouse: {}",
         box_coor_sum_double_wide
@@ -318,48 +739,220 @@ mod tests {
     #[test]
     fn test_process_ex_small() {
         let raw_dataset = read_input_file("input/day15_ex_small.txt");
-        let mut raw_dataset_split = raw_dataset.split("\n\n");
-        let raw_map_dataset = raw_dataset_split.next().unwrap();
-        let instructions = raw_dataset_split.next().unwrap().replace("\n", "");
+        let (raw_map_dataset, instructions) = split_input(&raw_dataset).unwrap();
 
-        let mut map = Map::new(raw_map_dataset);
-        let box_coor_sum = process_first(&mut map, &instructions);
+        let mut map = Map::new(raw_map_dataset, &Layout::with_width(1)).unwrap();
+        let box_coor_sum = process(&mut map, &instructions);
         assert_eq!(box_coor_sum, 2028);
 
-        let mut map_double_wide = MapDoubleWide::new(raw_map_dataset);
-        let box_coor_sum_double_wide = process_second(&mut map_double_wide, &instructions);
+        let mut map_double_wide = Map::new(raw_map_dataset, &Layout::with_width(2)).unwrap();
+        let box_coor_sum_double_wide = process(&mut map_double_wide, &instructions);
         assert_eq!(box_coor_sum_double_wide, 1751);
     }
 
+    #[test]
+    fn test_replay() {
+        let raw_dataset = read_input_file("input/day15_ex_small.txt");
+        let (raw_map_dataset, instructions) = split_input(&raw_dataset).unwrap();
+
+        let mut map = Map::new(raw_map_dataset, &Layout::with_width(1)).unwrap();
+        assert_eq!(map.render(), raw_map_dataset.trim_end());
+        let steps: Vec<ReplayStep> = Replay::new(&mut map, &instructions).collect();
+        assert_eq!(steps.len(), 15);
+        assert_eq!(
+            (steps[0].index, steps[0].direction, steps[0].blocked),
+            (1, '<', true)
+        );
+        assert!(!steps[1].blocked);
+        assert_eq!(
+            steps[14].state,
+            "########\n\
+             #....OO#\n\
+             ##.....#\n\
+             #.....O#\n\
+             #.#O@..#\n\
+             #...O..#\n\
+             #...O..#\n\
+             ########"
+        );
+
+        let mut map_double_wide = Map::new(raw_map_dataset, &Layout::with_width(2)).unwrap();
+        let mut replay = Replay::new(&mut map_double_wide, &instructions);
+        assert_eq!(replay.step(), Some((1, '<', true)));
+        assert_eq!(
+            replay.next().unwrap().state.lines().nth(1).unwrap(),
+            "##..@.[]..[]..##"
+        );
+        assert_eq!(to_step_ranges("0,3-5").unwrap(), vec![(0, 0), (3, 5)]);
+        assert!(to_step_ranges("5-3").is_err());
+    }
+
+    #[test]
+    fn test_custom_boxes() {
+        let raw_map_dataset = "#######\n#.....#\n#.OO@.#\n#.....#\n#######";
+        let mut map = Map::new(raw_map_dataset, &Layout::with_width(3)).unwrap();
+        assert_eq!(
+            map.render().lines().nth(2).unwrap(),
+            "###...[=][=]@.....###"
+        );
+        assert!(map.move_robot(&'<'));
+        assert!(map.move_robot(&'<'));
+        assert!(map.move_robot(&'<'));
+        assert!(!map.move_robot(&'<'));
+        assert_eq!(
+            map.render().lines().nth(2).unwrap(),
+            "###[=][=]@........###"
+        );
+        assert_eq!(map.get_box_sum_coordinate(), 203 + 206);
+
+        let l_shape = Layout {
+            scale: 2,
+            box_shape: to_box_shape("0,0;0,1;1,0").unwrap(),
+        };
+        let raw_map_dataset = "######\n#....#\n#.O..#\n#....#\n#.O..#\n#....#\n#.@..#\n######";
+        let mut map = Map::new(raw_map_dataset, &l_shape).unwrap();
+        assert!(map.move_robot(&'^'));
+        assert!(!map.move_robot(&'^'));
+        assert_eq!(
+            map.render(),
+            "############\n\
+             ##..[]....##\n\
+             ##..|.....##\n\
+             ##..[]....##\n\
+             ##..|.....##\n\
+             ##..@.....##\n\
+             ##........##\n\
+             ############"
+        );
+        assert_eq!(map.get_box_sum_coordinate(), 104 + 304);
+    }
+
+    #[test]
+    fn test_validation() {
+        let layout = Layout::with_width(1);
+        let errors = [
+            ("#####\n#...#\n#####", "Map must contain exactly one robot"),
+            ("#####\n#@.@#\n#####", "Map must contain exactly one robot"),
+            (
+                "#####\n#@.#\n#####",
+                "Map rows must all have the same width",
+            ),
+            ("#####\n#@x.#\n#####", "Unknown tile 'x' at row 1, col 2"),
+        ];
+        for (raw_map_dataset, expected) in errors {
+            let err = Map::new(raw_map_dataset, &layout).err().unwrap();
+            assert_eq!(err.to_string(), expected);
+        }
+        let l_shape = Layout {
+            scale: 1,
+            box_shape: vec![(0, 0), (1, 0)],
+        };
+        assert!(Map::new("..O\n..@", &l_shape).is_err());
+        assert!(Map::new("..@\nO..", &l_shape).is_err());
+
+        let mut map = Map::new("O@", &layout).unwrap();
+        assert!(!map.move_robot(&'^'));
+        assert!(!map.move_robot(&'<'));
+        assert!(!map.move_robot(&'v'));
+        assert!(!map.move_robot(&'>'));
+        assert_eq!(map.render(), "O@");
+
+        assert_eq!(
+            split_input("#@#\n\n<>\n^x").err().unwrap().to_string(),
+            "Invalid instruction 'x' at index 3"
+        );
+        assert!(split_input("#@#\n<>").is_err());
+    }
+
+    #[test]
+    fn test_history() {
+        let raw_dataset = read_input_file("input/day15_ex_small.txt");
+        let (raw_map_dataset, instructions) = split_input(&raw_dataset).unwrap();
+
+        let mut map = Map::new(raw_map_dataset, &Layout::with_width(2)).unwrap();
+        let initial_state = map.render();
+        process(&mut map, &instructions[..7]);
+        let half_state = map.render();
+        assert_eq!(map.get_instructions(), instructions[..7]);
+        process(&mut map, &instructions[7..]);
+        assert_eq!(map.get_instructions(), instructions);
+        assert_eq!(map.get_box_sum_coordinate(), 1751);
+
+        assert!((0..8).all(|_| map.undo()));
+        assert_eq!(map.render(), half_state);
+        assert_eq!(map.get_instructions(), instructions[..7]);
+        assert!((0..7).all(|_| map.undo()));
+        assert!(!map.undo());
+        assert_eq!(map.render(), initial_state);
+        assert!((0..15).all(|_| map.redo()));
+        assert!(!map.redo());
+        assert_eq!(map.get_box_sum_coordinate(), 1751);
+
+        let mut map = Map::new(raw_map_dataset, &Layout::with_width(1)).unwrap();
+        assert_eq!(
+            map.execute("<^^>>>").unwrap().unwrap().lines().next(),
+            Some("Blocked moves: 3")
+        );
+        assert!(map.execute("undo 3").is_ok());
+        assert_eq!(map.execute("i").unwrap().unwrap(), "<^^");
+        assert!(map.execute("redo").is_ok());
+        assert!(map.execute("<").is_ok());
+        assert!(!map.redo());
+        assert_eq!(map.get_instructions(), "<^^><");
+        assert!(map.execute("jump").is_err());
+        assert!(map.execute("q").unwrap().is_none());
+
+        let saved = Map::new(&map.render(), &Layout::with_width(1)).unwrap();
+        assert_eq!(saved.render(), map.render());
+        assert_eq!(saved.get_box_sum_coordinate(), map.get_box_sum_coordinate());
+
+        // wide boxes are saved as laid out and read back at scale 1
+        let mut map = Map::new(raw_map_dataset, &Layout::with_width(2)).unwrap();
+        process(&mut map, &instructions);
+        assert!(map.is_saveable());
+        let mut saved = Map::new(&map.render(), &Layout::with_width(1)).unwrap();
+        assert_eq!(saved.render(), map.render());
+        assert_eq!(saved.get_box_sum_coordinate(), 1751);
+        assert_eq!(saved.move_robot(&'<'), map.move_robot(&'<'));
+        assert_eq!(saved.render(), map.render());
+        assert!(Map::new(&map.render(), &Layout::with_width(2)).is_err());
+        assert!(Map::new("@[=.", &Layout::with_width(1)).is_err());
+        assert!(Map::new("@[=\n]..", &Layout::with_width(1)).is_err());
+
+        let tall_box = Layout {
+            scale: 1,
+            box_shape: vec![(0, 0), (1, 0)],
+        };
+        let mut map = Map::new("O@\n..", &tall_box).unwrap();
+        assert!(!map.is_saveable());
+        assert!(map.execute("save map.txt").is_err());
+    }
+
     #[test]
     fn test_process_ex() {
         let raw_dataset = read_input_file("input/day15_ex.txt");
-        let mut raw_dataset_split = raw_dataset.split("\n\n");
-        let raw_map_dataset = raw_dataset_split.next().unwrap();
-        let instructions = raw_dataset_split.next().unwrap().replace("\n", "");
+        let (raw_map_dataset, instructions) = split_input(&raw_dataset).unwrap();
 
-        let mut map = Map::new(raw_map_dataset);
-        let box_coor_sum = process_first(&mut map, &instructions);
+        let mut map = Map::new(raw_map_dataset, &Layout::with_width(1)).unwrap();
+        let box_coor_sum = process(&mut map, &instructions);
         assert_eq!(box_coor_sum, 10092);
 
-        let mut map_double_wide = MapDoubleWide::new(raw_map_dataset);
-        let box_coor_sum_double_wide = process_second(&mut map_double_wide, &instructions);
+        let mut map_double_wide = Map::new(raw_map_dataset, &Layout::with_width(2)).unwrap();
+        let box_coor_sum_double_wide = process(&mut map_double_wide, &instructions);
         assert_eq!(box_coor_sum_double_wide, 9021);
     }
 
     #[test]
     fn test_process() {
         let raw_dataset = read_input_file("input/day15.txt");
-        let mut raw_dataset_split = raw_dataset.split("\n\n");
-        let raw_map_dataset = raw_dataset_split.next().unwrap();
-        let instructions = raw_dataset_split.next().unwrap().replace("\n", "");
+        let (raw_map_dataset, instructions) = split_input(&raw_dataset).unwrap();
 
-        let mut map = Map::new(raw_map_dataset);
-        let box_coor_sum = process_first(&mut map, &instructions);
+        let mut map = Map::new(raw_map_dataset, &Layout::with_width(1)).unwrap();
+        let box_coor_sum = process(&mut map, &instructions);
         assert_eq!(box_coor_sum, 1563092);
 
-        let mut map_double_wide = MapDoubleWide::new(raw_map_dataset);
-        let box_coor_sum_double_wide = process_second(&mut map_double_wide, &instructions);
+        let mut map_double_wide = Map::new(raw_map_dataset, &Layout::with_width(2)).unwrap();
+        let box_coor_sum_double_wide = process(&mut map_double_wide, &instructions);
         assert_eq!(box_coor_sum_double_wide, 1582688);
     }
 }
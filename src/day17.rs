@@ -1,7 +1,23 @@
-use std::fs;
+use rayon::prelude::*;
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    io::{self, BufRead, BufReader},
+    time::Instant,
+};
+
+const MAX_STEPS: usize = 1_000_000;
+const BRUTE_FORCE_LIMIT: u64 = 1 << 24;
+
+enum Mode {
+    Run,
+    Debug(Option<String>),
+    Search(u64, u64),
+}
 
 struct Config {
     in_file: String,
+    mode: Mode,
 }
 
 impl Config {
@@ -10,8 +26,20 @@ impl Config {
             Some(arg) => arg,
             None => return Err("Missing input file argument"),
         };
+        let mode = match args.next().as_deref() {
+            None => Mode::Run,
+            Some("debug") => Mode::Debug(args.next()),
+            Some("search") => {
+                let mut next_register_a = || match args.next() {
+                    Some(arg) => arg.parse().map_err(|_| "Register A range must be numbers"),
+                    None => Err("Missing register A range arguments"),
+                };
+                Mode::Search(next_register_a()?, next_register_a()?)
+            }
+            Some(_) => return Err("Unknown mode argument, only `debug` or `search` is supported"),
+        };
 
-        Ok(Config { in_file })
+        Ok(Config { in_file, mode })
     }
 }
 
@@ -46,6 +74,18 @@ impl Operand {
     }
 }
 
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Literal(v) => write!(f, "{}", v),
+            Operand::Combo(4) => write!(f, "A"),
+            Operand::Combo(5) => write!(f, "B"),
+            Operand::Combo(6) => write!(f, "C"),
+            Operand::Combo(v) => write!(f, "{}", v),
+        }
+    }
+}
+
 enum Instruction {
     ADV(Operand),
     BXL(Operand),
@@ -57,9 +97,26 @@ enum Instruction {
     CDV(Operand),
 }
 
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::ADV(operand) => write!(f, "adv {}", operand),
+            Instruction::BXL(operand) => write!(f, "bxl {}", operand),
+            Instruction::BST(operand) => write!(f, "bst {}", operand),
+            Instruction::JNZ(operand) => write!(f, "jnz {}", operand),
+            Instruction::BXC => write!(f, "bxc"),
+            Instruction::OUT(operand) => write!(f, "out {}", operand),
+            Instruction::BDV(operand) => write!(f, "bdv {}", operand),
+            Instruction::CDV(operand) => write!(f, "cdv {}", operand),
+        }
+    }
+}
+
 struct ChronospatialComputer {
+    initial_registers: [u64; 3],
     registers: [u64; 3],
     instruction_pointer: usize,
+    program: Vec<u64>,
     instructions: Vec<Instruction>,
     output: Vec<u64>,
 }
@@ -72,9 +129,15 @@ impl ChronospatialComputer {
         }
         let raw_instructions = &raw_dataset.lines().skip(4).next().unwrap()[9..];
         let instructions = ChronospatialComputer::to_instructions(raw_instructions);
+        let program = raw_instructions
+            .split(',')
+            .map(|v| v.parse().unwrap())
+            .collect();
         ChronospatialComputer {
+            initial_registers: registers,
             registers,
             instruction_pointer: 0,
+            program,
             instructions,
             output: Vec::new(),
         }
@@ -129,76 +192,517 @@ impl ChronospatialComputer {
         }
     }
 
-    fn find_initial_register_a_from_output(output: &str) -> u64 {
-        let output_iter = output.split(',').map(|v| v.parse::<u64>().unwrap());
-        let k1 = output_iter.clone().skip(3).next().unwrap();
-        let k2 = output_iter.clone().skip(7).next().unwrap();
-        let get_out_b = |a: u64| -> u64 {
-            let b = a & 7 ^ k1;
-            let c = a >> b;
-            b ^ k2 ^ c & 7
+    // shifting by 64 bits or more clears every bit, like the division it stands for
+    fn get_shifted_a(&self, operand: &Operand) -> u64 {
+        u32::try_from(self.get_operant_value(operand))
+            .ok()
+            .and_then(|by| self.registers[0].checked_shr(by))
+            .unwrap_or(0)
+    }
+
+    fn reset(&mut self, register_a: u64) {
+        self.registers = self.initial_registers;
+        self.registers[0] = register_a;
+        self.instruction_pointer = 0;
+        self.output.clear();
+    }
+
+    fn output_with_register_a(&mut self, register_a: u64) -> Option<&[u64]> {
+        self.reset(register_a);
+        self.run(MAX_STEPS).ok()?;
+        Some(&self.output)
+    }
+
+    fn is_shift_a_by_3_loop(&self) -> bool {
+        let mut adv = self
+            .instructions
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::ADV(_)));
+        let mut jnz = self
+            .instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| matches!(instruction, Instruction::JNZ(_)));
+        let shift_by_3 = matches!(
+            (adv.next(), adv.next()),
+            (Some(Instruction::ADV(Operand::Combo(3))), None)
+        );
+        let loop_to_start = match (jnz.next(), jnz.next()) {
+            (Some((i, Instruction::JNZ(Operand::Literal(0)))), None) => {
+                i == self.instructions.len() - 1
+            }
+            _ => false,
         };
+        shift_by_3 && loop_to_start
+    }
 
-        output_iter
+    fn find_register_a_by_output_suffix(&mut self) -> Option<u64> {
+        let program = self.program.clone();
+        (0..program.len())
             .rev()
-            .fold(vec![0], |acc, v| {
-                acc.into_iter()
-                    .map(|a| a << 3)
-                    .flat_map(|a| (0..=7).map(move |frag| a | frag))
-                    .filter(|a| get_out_b(*a) == v)
-                    .collect()
-            })
+            .try_fold(vec![0], |candidates, i| {
+                let next_candidates: Vec<u64> = candidates
+                    .into_iter()
+                    .flat_map(|a| (0..=7).map(move |frag| a << 3 | frag))
+                    .filter(|&a| self.output_with_register_a(a) == Some(&program[i..]))
+                    .collect();
+                match next_candidates.is_empty() {
+                    true => None,
+                    false => Some(next_candidates),
+                }
+            })?
             .into_iter()
             .min()
-            .unwrap()
     }
 
-    fn run(&mut self) {
-        loop {
-            let instruction = self.instructions.get(self.instruction_pointer);
-            if instruction.is_none() {
-                break;
+    fn find_register_a_by_brute_force(&self, limit: u64) -> Option<u64> {
+        CompiledProgram::new(self).find_register_a_in_range(0, limit, &self.program, true)
+    }
+
+    fn find_initial_register_a_for_quine(
+        &mut self,
+        brute_force_limit: u64,
+    ) -> Result<u64, &'static str> {
+        let shift_by_3 = self.is_shift_a_by_3_loop();
+        let found = match shift_by_3 {
+            true => self.find_register_a_by_output_suffix(),
+            false => None,
+        };
+        let found = found.or_else(|| self.find_register_a_by_brute_force(brute_force_limit));
+        self.reset(self.initial_registers[0]);
+        match (found, shift_by_3) {
+            (Some(a), _) => Ok(a),
+            (None, true) => Err("No register A value makes the program output itself"),
+            (None, false) => Err(
+                "Program does not shift A by 3 per iteration and brute force found no register A value within limit",
+            ),
+        }
+    }
+
+    fn is_halted(&self) -> bool {
+        self.instruction_pointer >= self.instructions.len()
+    }
+
+    fn step(&mut self) -> bool {
+        let instruction = match self.instructions.get(self.instruction_pointer) {
+            Some(instruction) => instruction,
+            None => return false,
+        };
+        match instruction {
+            Instruction::ADV(operand) => {
+                self.registers[0] = self.get_shifted_a(operand);
             }
-            let instruction = instruction.unwrap();
-            match instruction {
-                Instruction::ADV(operand) => {
-                    self.registers[0] >>= self.get_operant_value(operand);
+            Instruction::BXL(operand) => {
+                self.registers[1] ^= self.get_operant_value(operand);
+            }
+            Instruction::BST(operand) => {
+                self.registers[1] = self.get_operant_value(operand) & 0b111;
+            }
+            Instruction::JNZ(operand) => {
+                if self.registers[0] != 0 {
+                    self.instruction_pointer = self.get_operant_value(operand) as usize;
+                    return true;
                 }
+            }
+            Instruction::BXC => {
+                self.registers[1] ^= self.registers[2];
+            }
+            Instruction::OUT(operand) => {
+                self.output.push(self.get_operant_value(operand) & 0b111);
+            }
+            Instruction::BDV(operand) => {
+                self.registers[1] = self.get_shifted_a(operand);
+            }
+            Instruction::CDV(operand) => {
+                self.registers[2] = self.get_shifted_a(operand);
+            }
+        }
+        self.instruction_pointer += 1;
+        true
+    }
+
+    fn run(&mut self, max_steps: usize) -> Result<usize, &'static str> {
+        let mut steps = 0;
+        while self.step() {
+            steps += 1;
+            if steps >= max_steps && !self.is_halted() {
+                return Err("Exceeded maximum steps, program might not terminate");
+            }
+        }
+        Ok(steps)
+    }
+
+    fn get_output(&self) -> String {
+        self.output
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Value {
+    Literal(u64),
+    Register(usize),
+}
+
+impl Value {
+    fn from_operand(operand: &Operand) -> Value {
+        match operand {
+            Operand::Literal(v) => Value::Literal(*v),
+            Operand::Combo(v) if *v <= 3 => Value::Literal(*v as u64),
+            Operand::Combo(v) => Value::Register(*v as usize - 4),
+        }
+    }
+
+    #[inline(always)]
+    fn get(&self, registers: &[u64; 3]) -> u64 {
+        match self {
+            Value::Literal(v) => *v,
+            Value::Register(register) => registers[*register],
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum MicroOp {
+    ShiftA(usize, Value),
+    XorB(u64),
+    StoreB(Value),
+    JumpIfA(usize),
+    XorBC,
+    Out(Value),
+}
+
+struct CompiledProgram {
+    initial_registers: [u64; 3],
+    ops: Vec<MicroOp>,
+}
+
+impl CompiledProgram {
+    fn new(computer: &ChronospatialComputer) -> CompiledProgram {
+        let ops = computer
+            .instructions
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::ADV(operand) => MicroOp::ShiftA(0, Value::from_operand(operand)),
                 Instruction::BXL(operand) => {
-                    self.registers[1] ^= self.get_operant_value(operand);
-                }
-                Instruction::BST(operand) => {
-                    self.registers[1] = self.get_operant_value(operand) & 0b111;
+                    MicroOp::XorB(Value::from_operand(operand).get(&[0; 3]))
                 }
+                Instruction::BST(operand) => MicroOp::StoreB(Value::from_operand(operand)),
                 Instruction::JNZ(operand) => {
-                    if self.registers[0] != 0 {
-                        self.instruction_pointer = self.get_operant_value(operand) as usize;
-                        continue;
-                    }
-                }
-                Instruction::BXC => {
-                    self.registers[1] ^= self.registers[2];
+                    MicroOp::JumpIfA(Value::from_operand(operand).get(&[0; 3]) as usize)
                 }
-                Instruction::OUT(operand) => {
-                    self.output.push(self.get_operant_value(operand) & 0b111);
+                Instruction::BXC => MicroOp::XorBC,
+                Instruction::OUT(operand) => MicroOp::Out(Value::from_operand(operand)),
+                Instruction::BDV(operand) => MicroOp::ShiftA(1, Value::from_operand(operand)),
+                Instruction::CDV(operand) => MicroOp::ShiftA(2, Value::from_operand(operand)),
+            })
+            .collect();
+        CompiledProgram {
+            initial_registers: computer.initial_registers,
+            ops,
+        }
+    }
+
+    // returns false as soon as `emit` rejects a value or the step limit is hit
+    #[inline(always)]
+    fn execute(&self, register_a: u64, mut emit: impl FnMut(u64) -> bool) -> bool {
+        let mut registers = self.initial_registers;
+        registers[0] = register_a;
+        let mut instruction_pointer = 0;
+        for _ in 0..MAX_STEPS {
+            let op = match self.ops.get(instruction_pointer) {
+                Some(op) => op,
+                None => return true,
+            };
+            instruction_pointer += 1;
+            match *op {
+                MicroOp::ShiftA(register, value) => {
+                    registers[register] = u32::try_from(value.get(&registers))
+                        .ok()
+                        .and_then(|by| registers[0].checked_shr(by))
+                        .unwrap_or(0);
                 }
-                Instruction::BDV(operand) => {
-                    self.registers[1] = self.registers[0] >> self.get_operant_value(operand);
+                MicroOp::XorB(v) => registers[1] ^= v,
+                MicroOp::StoreB(value) => registers[1] = value.get(&registers) & 0b111,
+                MicroOp::JumpIfA(target) => {
+                    if registers[0] != 0 {
+                        instruction_pointer = target;
+                    }
                 }
-                Instruction::CDV(operand) => {
-                    self.registers[2] = self.registers[0] >> self.get_operant_value(operand);
+                MicroOp::XorBC => registers[1] ^= registers[2],
+                MicroOp::Out(value) => {
+                    if !emit(value.get(&registers) & 0b111) {
+                        return false;
+                    }
                 }
             }
-            self.instruction_pointer += 1;
         }
+        false
     }
 
-    fn get_output(&self) -> String {
-        self.output
+    fn is_output(&self, register_a: u64, expected: &[u64]) -> bool {
+        let mut expected_iter = expected.iter();
+        self.execute(register_a, |v| expected_iter.next() == Some(&v))
+            && expected_iter.next().is_none()
+    }
+
+    fn find_register_a_in_range(
+        &self,
+        from: u64,
+        to: u64,
+        expected: &[u64],
+        parallel: bool,
+    ) -> Option<u64> {
+        match parallel {
+            true => (from..to)
+                .into_par_iter()
+                .find_first(|&a| self.is_output(a, expected)),
+            false => (from..to).find(|&a| self.is_output(a, expected)),
+        }
+    }
+}
+
+const REGISTER_NAMES: [char; 3] = ['A', 'B', 'C'];
+
+struct TraceRecord {
+    step: usize,
+    instruction_pointer: usize,
+    instruction: String,
+    registers: [u64; 3],
+}
+
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "#{} ip={} {:<8} A={} B={} C={}",
+            self.step,
+            self.instruction_pointer,
+            self.instruction,
+            self.registers[0],
+            self.registers[1],
+            self.registers[2]
+        )
+    }
+}
+
+struct Debugger {
+    computer: ChronospatialComputer,
+    breakpoints: HashSet<usize>,
+    watches: HashSet<usize>,
+    trace: Vec<TraceRecord>,
+    max_steps: usize,
+}
+
+impl Debugger {
+    fn new(computer: ChronospatialComputer, max_steps: usize) -> Debugger {
+        Debugger {
+            computer,
+            breakpoints: HashSet::new(),
+            watches: HashSet::new(),
+            trace: Vec::new(),
+            max_steps,
+        }
+    }
+
+    fn to_register(name: Option<&str>) -> Result<usize, &'static str> {
+        match name.map(|name| name.to_ascii_uppercase()).as_deref() {
+            Some("A") => Ok(0),
+            Some("B") => Ok(1),
+            Some("C") => Ok(2),
+            _ => Err("Register must be one of A, B or C"),
+        }
+    }
+
+    fn to_number(arg: Option<&str>, default: Option<usize>) -> Result<usize, &'static str> {
+        match arg {
+            Some(arg) => arg.parse().map_err(|_| "Argument must be a number"),
+            None => default.ok_or("Missing number argument"),
+        }
+    }
+
+    fn step(&mut self) -> Result<bool, &'static str> {
+        if self.computer.is_halted() {
+            return Ok(false);
+        }
+        if self.trace.len() >= self.max_steps {
+            return Err("Exceeded maximum steps, program might not terminate");
+        }
+        let instruction_pointer = self.computer.instruction_pointer;
+        let instruction = self.computer.instructions[instruction_pointer].to_string();
+        self.computer.step();
+        self.trace.push(TraceRecord {
+            step: self.trace.len() + 1,
+            instruction_pointer,
+            instruction,
+            registers: self.computer.registers,
+        });
+        Ok(true)
+    }
+
+    fn continue_run(&mut self) -> Result<String, &'static str> {
+        loop {
+            let before = self.computer.registers;
+            if !self.step()? {
+                return Ok(format!("Program halted\n{}", self.get_state()));
+            }
+            if let Some(&register) = self
+                .watches
+                .iter()
+                .find(|&&register| before[register] != self.computer.registers[register])
+            {
+                return Ok(format!(
+                    "Register {} changed {} -> {}\n{}",
+                    REGISTER_NAMES[register],
+                    before[register],
+                    self.computer.registers[register],
+                    self.get_state()
+                ));
+            }
+            if self
+                .breakpoints
+                .contains(&self.computer.instruction_pointer)
+            {
+                return Ok(format!(
+                    "Breakpoint at ip={}\n{}",
+                    self.computer.instruction_pointer,
+                    self.get_state()
+                ));
+            }
+        }
+    }
+
+    fn get_state(&self) -> String {
+        let next_instruction = match self
+            .computer
+            .instructions
+            .get(self.computer.instruction_pointer)
+        {
+            Some(instruction) => instruction.to_string(),
+            None => "halted".to_string(),
+        };
+        let watched = REGISTER_NAMES
             .iter()
-            .map(|n| n.to_string())
+            .enumerate()
+            .filter(|(register, _)| self.watches.contains(register))
+            .map(|(_, name)| name.to_string())
             .collect::<Vec<String>>()
-            .join(",")
+            .join(",");
+        format!(
+            "ip={} next={} A={} B={} C={} steps={} watching=[{}]",
+            self.computer.instruction_pointer,
+            next_instruction,
+            self.computer.registers[0],
+            self.computer.registers[1],
+            self.computer.registers[2],
+            self.trace.len(),
+            watched
+        )
+    }
+
+    fn get_trace(&self) -> String {
+        self.trace
+            .iter()
+            .map(|record| record.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn execute(&mut self, command: &str) -> Result<Option<String>, &'static str> {
+        let mut parts = command.split_whitespace();
+        let message = match parts.next() {
+            None => String::new(),
+            Some("step" | "s") => {
+                let count = Debugger::to_number(parts.next(), Some(1))?;
+                for _ in 0..count {
+                    if !self.step()? {
+                        break;
+                    }
+                }
+                self.get_state()
+            }
+            Some("continue" | "c") => self.continue_run()?,
+            Some("break" | "b") => {
+                let instruction_pointer = Debugger::to_number(parts.next(), None)?;
+                self.breakpoints.insert(instruction_pointer);
+                format!("Breakpoint set at ip={}", instruction_pointer)
+            }
+            Some("delete" | "d") => {
+                let instruction_pointer = Debugger::to_number(parts.next(), None)?;
+                if !self.breakpoints.remove(&instruction_pointer) {
+                    return Err("No breakpoint at that instruction pointer");
+                }
+                format!("Breakpoint removed at ip={}", instruction_pointer)
+            }
+            Some("watch" | "w") => {
+                let register = Debugger::to_register(parts.next())?;
+                self.watches.insert(register);
+                format!("Watching register {}", REGISTER_NAMES[register])
+            }
+            Some("unwatch") => {
+                let register = Debugger::to_register(parts.next())?;
+                self.watches.remove(&register);
+                format!("Stopped watching register {}", REGISTER_NAMES[register])
+            }
+            Some("limit") => {
+                self.max_steps = Debugger::to_number(parts.next(), None)?;
+                format!("Maximum steps set to {}", self.max_steps)
+            }
+            Some("regs" | "r") => self.get_state(),
+            Some("output" | "o") => format!("Output: {}", self.computer.get_output()),
+            Some("trace" | "t") => match parts.next() {
+                Some(out_file) => {
+                    fs::write(out_file, self.get_trace() + "\n")
+                        .map_err(|_| "Problem writing trace file")?;
+                    format!(
+                        "Trace of {} steps written to {}",
+                        self.trace.len(),
+                        out_file
+                    )
+                }
+                None => self.get_trace(),
+            },
+            Some("quit" | "q") => return Ok(None),
+            Some("help" | "h") => concat!(
+                "step|s [n]       execute n instructions (default 1)\n",
+                "continue|c       run until breakpoint, watched register change or halt\n",
+                "break|b <ip>     set breakpoint on instruction pointer\n",
+                "delete|d <ip>    remove breakpoint\n",
+                "watch|w <A|B|C>  stop when register changes\n",
+                "unwatch <A|B|C>  stop watching register\n",
+                "limit <n>        set maximum executed steps\n",
+                "regs|r           print registers and instruction pointer\n",
+                "output|o         print output so far\n",
+                "trace|t [file]   dump execution trace\n",
+                "quit|q           leave debugger"
+            )
+            .to_string(),
+            Some(_) => return Err("Unknown command, try `help`"),
+        };
+        Ok(Some(message))
+    }
+
+    fn session(&mut self, input: impl BufRead, echo: bool) {
+        println!("{}", self.get_state());
+        for line in input.lines() {
+            let command = line.unwrap_or_else(|err| {
+                eprintln!("Problem reading command: {}", err);
+                std::process::exit(1);
+            });
+            if echo {
+                println!("> {}", command);
+            }
+            match self.execute(&command) {
+                Ok(Some(message)) if message.is_empty() => {}
+                Ok(Some(message)) => println!("{}", message),
+                Ok(None) => break,
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        println!("Output: {}", self.computer.get_output());
     }
 }
 
@@ -211,14 +715,54 @@ pub fn run(mut args: impl Iterator<Item = String>) {
 
     let raw_dataset = read_input_file(&config.in_file);
     let mut computer = ChronospatialComputer::new(&raw_dataset);
-    computer.run();
+    match config.mode {
+        Mode::Run => {}
+        Mode::Debug(script_file) => {
+            let mut debugger = Debugger::new(computer, MAX_STEPS);
+            match script_file {
+                Some(script_file) => {
+                    let script = fs::File::open(&script_file).unwrap_or_else(|err| {
+                        eprintln!("Problem reading script file: {}", err);
+                        std::process::exit(1);
+                    });
+                    debugger.session(BufReader::new(script), true);
+                }
+                None => debugger.session(io::stdin().lock(), false),
+            }
+            return;
+        }
+        Mode::Search(from, to) => {
+            let compiled = CompiledProgram::new(&computer);
+            let start = Instant::now();
+            let found = compiled.find_register_a_in_range(from, to, &computer.program, true);
+            let elapsed = start.elapsed().as_secs_f64();
+            match found {
+                Some(a) => println!("register A cause output as input: {}", a),
+                None => println!("No register A in {}..{} cause output as input", from, to),
+            }
+            let searched = found.map_or(to, |a| a + 1).saturating_sub(from);
+            println!(
+                "Searched {} values in {:.3}s ({:.0} values/s)",
+                searched,
+                elapsed,
+                searched as f64 / elapsed
+            );
+            return;
+        }
+    }
+    if let Err(err) = computer.run(MAX_STEPS) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
     let first_output = computer.get_output();
     println!("Program output: {first_output}");
 
-    let original_instructions = raw_dataset.lines().skip(4).next().unwrap()[9..].to_string();
-    let init_register_a =
-        ChronospatialComputer::find_initial_register_a_from_output(&original_instructions);
-    println!("register A cause output as input: {}", init_register_a);
+    match computer.find_initial_register_a_for_quine(BRUTE_FORCE_LIMIT) {
+        Ok(init_register_a) => {
+            println!("register A cause output as input: {}", init_register_a)
+        }
+        Err(err) => eprintln!("{}", err),
+    }
 }
 
 #[cfg(test)]
@@ -231,21 +775,103 @@ mod tests {
         let config = Config::new(&mut args).unwrap();
         let raw_dataset = read_input_file(&config.in_file);
         let mut computer = ChronospatialComputer::new(&raw_dataset);
-        computer.run();
+        computer.run(MAX_STEPS).unwrap();
         assert_eq!(computer.get_output(), "4,6,3,5,6,3,5,2,1,0");
     }
 
+    #[test]
+    fn test_debugger() {
+        let raw_dataset = read_input_file("input/day17_ex.txt");
+        let mut debugger = Debugger::new(ChronospatialComputer::new(&raw_dataset), 10);
+        debugger.execute("step 2").unwrap();
+        assert_eq!(debugger.computer.instruction_pointer, 2);
+        assert_eq!(debugger.computer.get_output(), "4");
+        debugger.execute("break 1").unwrap();
+        debugger.execute("continue").unwrap();
+        assert_eq!(debugger.computer.instruction_pointer, 1);
+        assert_eq!(debugger.computer.registers[0], 182);
+        assert_eq!(debugger.trace.len(), 4);
+        assert_eq!(
+            debugger.get_trace().lines().next().unwrap(),
+            "#1 ip=0 adv 1    A=364 B=0 C=0"
+        );
+        debugger.execute("delete 1").unwrap();
+        debugger.execute("watch a").unwrap();
+        debugger.execute("continue").unwrap();
+        assert_eq!(debugger.computer.registers[0], 91);
+        debugger.execute("continue").unwrap();
+        assert_eq!(debugger.computer.registers[0], 45);
+        assert!(debugger.execute("continue").is_err());
+        assert!(debugger.execute("quit").unwrap().is_none());
+
+        let raw_dataset = "Register A: 1\nRegister B: 0\nRegister C: 0\n\nProgram: 3,0";
+        let mut computer = ChronospatialComputer::new(raw_dataset);
+        assert!(computer.run(1000).is_err());
+    }
+
     #[test]
     fn test_process() {
         let mut args = vec!["input/day17.txt".to_string()].into_iter();
         let config = Config::new(&mut args).unwrap();
         let raw_dataset = read_input_file(&config.in_file);
         let mut computer = ChronospatialComputer::new(&raw_dataset);
-        computer.run();
+        computer.run(MAX_STEPS).unwrap();
         assert_eq!(computer.get_output(), "1,2,3,1,3,2,5,3,1");
-        let init_register_a = ChronospatialComputer::find_initial_register_a_from_output(
-            "2,4,1,5,7,5,1,6,0,3,4,3,5,5,3,0",
-        );
+        let init_register_a = computer.find_initial_register_a_for_quine(0).unwrap();
         assert_eq!(init_register_a, 105706277661082);
     }
+
+    #[test]
+    fn test_compiled_program() {
+        for (in_file, register_a) in [("input/day17_ex.txt", 729), ("input/day17.txt", 34615120)] {
+            let raw_dataset = read_input_file(in_file);
+            let mut computer = ChronospatialComputer::new(&raw_dataset);
+            let compiled = CompiledProgram::new(&computer);
+            computer.run(MAX_STEPS).unwrap();
+            assert!(compiled.is_output(register_a, &computer.output));
+            assert!(!compiled.is_output(register_a, &computer.output[1..]));
+        }
+
+        let raw_dataset = "Register A: 2024\nRegister B: 0\nRegister C: 0\n\nProgram: 0,3,5,4,3,0";
+        let computer = ChronospatialComputer::new(raw_dataset);
+        let compiled = CompiledProgram::new(&computer);
+        let program = &computer.program;
+        assert_eq!(
+            compiled.find_register_a_in_range(0, 1 << 18, program, true),
+            Some(117440)
+        );
+        assert_eq!(
+            compiled.find_register_a_in_range(0, 1 << 17, program, false),
+            Some(117440)
+        );
+        assert_eq!(
+            compiled.find_register_a_in_range(0, 117440, program, true),
+            None
+        );
+
+        let raw_dataset = "Register A: 1\nRegister B: 0\nRegister C: 0\n\nProgram: 3,0";
+        let compiled = CompiledProgram::new(&ChronospatialComputer::new(raw_dataset));
+        assert!(!compiled.is_output(1, &[]));
+
+        // shifting A by itself goes past 64 bits, both backends must clear the register
+        let raw_dataset = "Register A: 100\nRegister B: 0\nRegister C: 0\n\nProgram: 7,4,5,6,5,4";
+        let mut computer = ChronospatialComputer::new(raw_dataset);
+        let compiled = CompiledProgram::new(&computer);
+        computer.run(MAX_STEPS).unwrap();
+        assert_eq!(computer.get_output(), "0,4");
+        assert!(compiled.is_output(100, &computer.output));
+    }
+
+    #[test]
+    fn test_quine_search() {
+        let raw_dataset = "Register A: 2024\nRegister B: 0\nRegister C: 0\n\nProgram: 0,3,5,4,3,0";
+        let mut computer = ChronospatialComputer::new(raw_dataset);
+        assert_eq!(computer.find_initial_register_a_for_quine(0), Ok(117440));
+        assert_eq!(computer.registers[0], 2024);
+
+        let raw_dataset = "Register A: 2024\nRegister B: 0\nRegister C: 0\n\nProgram: 0,1,5,4,3,0";
+        let mut computer = ChronospatialComputer::new(raw_dataset);
+        assert!(!computer.is_shift_a_by_3_loop());
+        assert!(computer.find_initial_register_a_for_quine(1 << 10).is_err());
+    }
 }
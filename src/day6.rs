@@ -1,10 +1,42 @@
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
-use std::sync::atomic::AtomicUsize;
-use std::sync::Arc;
-use std::{fs, thread};
+use std::fs;
 
 struct Config {
     in_file: String,
+    render: bool,
+    json_file: Option<String>,
+    patrol: bool,
+    events: Vec<ObstacleEvent>,
+    max_ticks: usize,
+}
+
+const MAX_TICKS: usize = 1_000_000;
+
+#[derive(Clone, Debug, PartialEq)]
+struct ObstacleEvent {
+    tick: usize,
+    row: u8,
+    col: u8,
+    add: bool,
+}
+
+impl ObstacleEvent {
+    // parses `TICK:ROW,COL`
+    fn from_str(raw_event: &str, add: bool) -> Result<ObstacleEvent, &'static str> {
+        let error = "Obstacle event must be TICK:ROW,COL";
+        let (tick, position) = raw_event.split_once(':').ok_or(error)?;
+        let (row, col) = position.split_once(',').ok_or(error)?;
+        match (tick.parse(), row.parse(), col.parse()) {
+            (Ok(tick), Ok(row), Ok(col)) => Ok(ObstacleEvent {
+                tick,
+                row,
+                col,
+                add,
+            }),
+            _ => Err(error),
+        }
+    }
 }
 
 impl Config {
@@ -13,8 +45,39 @@ impl Config {
             Some(arg) => arg,
             None => return Err("Missing input file argument"),
         };
-
-        Ok(Config { in_file })
+        let mut render = false;
+        let mut json_file = None;
+        let mut patrol = false;
+        let mut events = Vec::new();
+        let mut max_ticks = MAX_TICKS;
+        while let Some(option) = args.next() {
+            let mut value = || args.next().ok_or("Missing option value");
+            match option.as_str() {
+                "--render" => render = true,
+                "--json" => json_file = Some(value()?),
+                "--patrol" => patrol = true,
+                "--add" => events.push(ObstacleEvent::from_str(&value()?, true)?),
+                "--remove" => events.push(ObstacleEvent::from_str(&value()?, false)?),
+                "--max-ticks" => {
+                    max_ticks = value()?.parse().map_err(|_| "Max ticks must be a number")?
+                }
+                _ => {
+                    return Err(
+                        "Unknown option, expected --render, --json, --patrol, --add, --remove or --max-ticks",
+                    )
+                }
+            }
+        }
+        events.sort_by_key(|event| event.tick);
+
+        Ok(Config {
+            in_file,
+            render,
+            json_file,
+            patrol: patrol || !events.is_empty(),
+            events,
+            max_ticks,
+        })
     }
 }
 
@@ -34,6 +97,15 @@ enum Direction {
 }
 
 impl Direction {
+    fn to_char(&self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::South => 'v',
+            Direction::East => '>',
+            Direction::West => '<',
+        }
+    }
+
     fn from_char(facing: &char) -> Direction {
         match facing {
             '^' => Direction::North,
@@ -44,6 +116,15 @@ impl Direction {
         }
     }
 
+    fn index(&self) -> usize {
+        match self {
+            Direction::North => 0,
+            Direction::East => 1,
+            Direction::South => 2,
+            Direction::West => 3,
+        }
+    }
+
     fn turn_right(&self) -> Direction {
         match self {
             Direction::North => Direction::East,
@@ -77,9 +158,40 @@ impl GuardPosition {
         })
     }
 
+    fn all_from_maps(raw_dataset: &str) -> Result<Vec<GuardPosition>, &'static str> {
+        let guards: Vec<GuardPosition> = raw_dataset
+            .lines()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| matches!(c, '^' | 'v' | '<' | '>'))
+                    .map(move |(col, c)| GuardPosition {
+                        facing: Direction::from_char(&c),
+                        row: row as u8,
+                        col: col as u8,
+                    })
+            })
+            .collect();
+        match guards.is_empty() {
+            true => Err("No guard found"),
+            false => Ok(guards),
+        }
+    }
+
     fn turn_right(&mut self) {
         self.facing = self.facing.turn_right();
     }
+
+    fn next_position(&self, map_size: &MapSize) -> Option<(u8, u8)> {
+        match self.facing {
+            Direction::North => Some((self.row.checked_sub(1)?, self.col)),
+            Direction::South if self.row + 1 < map_size.height => Some((self.row + 1, self.col)),
+            Direction::West => Some((self.row, self.col.checked_sub(1)?)),
+            Direction::East if self.col + 1 < map_size.width => Some((self.row, self.col + 1)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -114,22 +226,34 @@ impl ObstacleHashMap {
         ObstacleHashMap { rows, cols }
     }
 
+    fn contains(&self, row: u8, col: u8) -> bool {
+        self.rows
+            .get(&row)
+            .is_some_and(|obstacle_row| obstacle_row.binary_search(&col).is_ok())
+    }
+
     fn add_obstacle(&mut self, row: u8, col: u8) {
-        let obstacle_row = self.rows.entry(row).or_insert_with(Vec::new);
-        obstacle_row.push(col);
-        obstacle_row.sort();
-        let obstacle_col = self.cols.entry(col).or_insert_with(Vec::new);
-        obstacle_col.push(row);
-        obstacle_col.sort();
+        let obstacle_row = self.rows.entry(row).or_default();
+        if let Err(i) = obstacle_row.binary_search(&col) {
+            obstacle_row.insert(i, col);
+        }
+        let obstacle_col = self.cols.entry(col).or_default();
+        if let Err(i) = obstacle_col.binary_search(&row) {
+            obstacle_col.insert(i, row);
+        }
     }
 
     fn remove_obstacle(&mut self, row: u8, col: u8) {
-        let obstacle_row = self.rows.get_mut(&row).unwrap();
-        let row_i = obstacle_row.iter().position(|&c| c == col).unwrap();
-        obstacle_row.remove(row_i);
-        let obstacle_col = self.cols.get_mut(&col).unwrap();
-        let col_i = obstacle_col.iter().position(|&r| r == row).unwrap();
-        obstacle_col.remove(col_i);
+        if let Some(obstacle_row) = self.rows.get_mut(&row) {
+            if let Ok(i) = obstacle_row.binary_search(&col) {
+                obstacle_row.remove(i);
+            }
+        }
+        if let Some(obstacle_col) = self.cols.get_mut(&col) {
+            if let Ok(i) = obstacle_col.binary_search(&row) {
+                obstacle_col.remove(i);
+            }
+        }
     }
 }
 
@@ -296,57 +420,370 @@ fn process_first(movement_records: &MovementRecords) -> usize {
     visited - crossed
 }
 
-fn process_second(
-    map_size: &MapSize,
-    movement_records: &MovementRecords,
-    obstacles: &mut ObstacleHashMap,
-    guard_original: &GuardPosition,
-) -> usize {
-    let mut visited: HashSet<(u8, u8)> = HashSet::new();
-    for (&row_i, paths_row) in movement_records.rows.iter() {
-        for path_row in paths_row {
-            let lower_col = path_row.0.min(path_row.1);
-            let upper_col = path_row.0.max(path_row.1);
-            for col_i in lower_col..=upper_col {
-                visited.insert((row_i, col_i));
+// for each (cell, direction) the cell where the guard stops in front of an obstacle,
+// or None when the guard walks off the map
+struct JumpTable {
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    jumps: Vec<Option<usize>>,
+}
+
+impl JumpTable {
+    fn new(map_size: &MapSize, obstacles: &ObstacleHashMap) -> JumpTable {
+        let width = map_size.width as usize;
+        let height = map_size.height as usize;
+        let mut blocked = vec![false; width * height];
+        for (row, cols) in obstacles.rows.iter() {
+            for col in cols {
+                blocked[*row as usize * width + *col as usize] = true;
+            }
+        }
+        let mut table = JumpTable {
+            width,
+            height,
+            blocked,
+            jumps: vec![None; width * height * 4],
+        };
+        // the next cell of North and West is visited before, of South and East after
+        for cell in 0..width * height {
+            for facing in [Direction::North, Direction::West] {
+                table.jumps[cell * 4 + facing.index()] = table.compute_jump(cell, &facing);
             }
         }
+        for cell in (0..width * height).rev() {
+            for facing in [Direction::South, Direction::East] {
+                table.jumps[cell * 4 + facing.index()] = table.compute_jump(cell, &facing);
+            }
+        }
+        table
+    }
+
+    fn compute_jump(&self, cell: usize, facing: &Direction) -> Option<usize> {
+        let next = self.next_cell(cell, facing)?;
+        match self.blocked[next] {
+            true => Some(cell),
+            false => self.jumps[next * 4 + facing.index()],
+        }
+    }
+
+    fn next_cell(&self, cell: usize, facing: &Direction) -> Option<usize> {
+        let (row, col) = (cell / self.width, cell % self.width);
+        match facing {
+            Direction::North if row > 0 => Some(cell - self.width),
+            Direction::South if row + 1 < self.height => Some(cell + self.width),
+            Direction::West if col > 0 => Some(cell - 1),
+            Direction::East if col + 1 < self.width => Some(cell + 1),
+            _ => None,
+        }
     }
-    for (&col_i, paths_col) in movement_records.cols.iter() {
-        for path_col in paths_col {
-            let lower_row = path_col.0.min(path_col.1);
-            let upper_row = path_col.0.max(path_col.1);
-            for row_i in lower_row..=upper_row {
-                visited.insert((row_i, col_i));
+
+    // steps from `cell` to `target` when it lies straight ahead
+    fn distance_ahead(&self, cell: usize, facing: &Direction, target: usize) -> Option<usize> {
+        let (row, col) = (cell / self.width, cell % self.width);
+        let (target_row, target_col) = (target / self.width, target % self.width);
+        match facing {
+            Direction::North if col == target_col && target_row < row => Some(row - target_row),
+            Direction::South if col == target_col && target_row > row => Some(target_row - row),
+            Direction::West if row == target_row && target_col < col => Some(col - target_col),
+            Direction::East if row == target_row && target_col > col => Some(target_col - col),
+            _ => None,
+        }
+    }
+
+    fn is_loop(&self, start: usize, facing: &Direction, obstruction: usize) -> bool {
+        let mut stopped = vec![false; self.jumps.len()];
+        let mut cell = start;
+        let mut facing = facing.clone();
+        loop {
+            let stop = self.jumps[cell * 4 + facing.index()];
+            // the patched obstruction wins when it is reached before the precomputed stop
+            let stop = match self.distance_ahead(cell, &facing, obstruction) {
+                Some(to_obstruction)
+                    if stop.is_none_or(|stop| {
+                        to_obstruction <= self.distance_ahead(cell, &facing, stop).unwrap_or(0)
+                    }) =>
+                {
+                    Some(self.step_back(obstruction, &facing))
+                }
+                _ => stop,
+            };
+            let stop = match stop {
+                Some(stop) => stop,
+                None => return false,
+            };
+            if stopped[stop * 4 + facing.index()] {
+                return true;
+            }
+            stopped[stop * 4 + facing.index()] = true;
+            cell = stop;
+            facing = facing.turn_right();
+        }
+    }
+
+    fn step_back(&self, cell: usize, facing: &Direction) -> usize {
+        match facing {
+            Direction::North => cell + self.width,
+            Direction::South => cell - self.width,
+            Direction::West => cell + 1,
+            Direction::East => cell - 1,
+        }
+    }
+
+    // each cell of the original walk but the start, with the guard state right before first
+    // stepping on it, an obstruction cannot be placed where the guard stands
+    fn get_first_visits(&self, guard: &GuardPosition) -> Vec<(usize, usize, Direction)> {
+        let start = guard.row as usize * self.width + guard.col as usize;
+        let mut visited = vec![false; self.blocked.len()];
+        let mut states = vec![false; self.jumps.len()];
+        let mut first_visits = Vec::new();
+        visited[start] = true;
+        let mut cell = start;
+        let mut facing = guard.facing.clone();
+        while !states[cell * 4 + facing.index()] {
+            states[cell * 4 + facing.index()] = true;
+            let next = match self.next_cell(cell, &facing) {
+                Some(next) => next,
+                None => break,
+            };
+            if self.blocked[next] {
+                facing = facing.turn_right();
+                continue;
             }
+            if !visited[next] {
+                visited[next] = true;
+                first_visits.push((next, cell, facing.clone()));
+            }
+            cell = next;
         }
+        first_visits
     }
-    let count = Arc::new(AtomicUsize::new(0));
-    let thread_count = 8;
-    let per_thread = visited.len() / thread_count;
-    thread::scope(|scope| {
-        let calc = |thread| {
-            let count = Arc::clone(&count);
-            for (row, col) in (&visited)
+}
+
+fn find_loop_obstructions(
+    map_size: &MapSize,
+    obstacles: &ObstacleHashMap,
+    guard_original: &GuardPosition,
+) -> Vec<(u8, u8)> {
+    let jump_table = JumpTable::new(map_size, obstacles);
+    let mut obstructions: Vec<(u8, u8)> = jump_table
+        .get_first_visits(guard_original)
+        .into_par_iter()
+        .filter(|(obstruction, start, facing)| jump_table.is_loop(*start, facing, *obstruction))
+        .map(|(obstruction, _, _)| {
+            (
+                (obstruction / jump_table.width) as u8,
+                (obstruction % jump_table.width) as u8,
+            )
+        })
+        .collect();
+    obstructions.sort();
+    obstructions
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PatrolStatus {
+    Patrolling,
+    Left(usize),
+    Looped(usize),
+}
+
+struct PatrollingGuard {
+    position: GuardPosition,
+    visited: HashSet<(u8, u8)>,
+    status: PatrolStatus,
+}
+
+// guards move one at a time in map order and turn right when an obstacle or another guard
+// is in front of them, events apply at the start of their tick
+fn simulate_patrol(
+    map_size: &MapSize,
+    obstacles: &ObstacleHashMap,
+    guards: &[GuardPosition],
+    events: &[ObstacleEvent],
+    max_ticks: usize,
+) -> Vec<PatrollingGuard> {
+    let mut obstacles = obstacles.clone();
+    let mut guards: Vec<PatrollingGuard> = guards
+        .iter()
+        .map(|guard| PatrollingGuard {
+            position: guard.clone(),
+            visited: HashSet::from([(guard.row, guard.col)]),
+            status: PatrolStatus::Patrolling,
+        })
+        .collect();
+    let last_event_tick = events.iter().map(|event| event.tick).max();
+    let mut events = events.iter().peekable();
+    let mut seen_states = HashMap::new();
+    for tick in 0..max_ticks {
+        while let Some(event) = events.next_if(|event| event.tick == tick) {
+            match event.add {
+                true => obstacles.add_obstacle(event.row, event.col),
+                false => obstacles.remove_obstacle(event.row, event.col),
+            }
+        }
+        // once all events are applied the patrol is deterministic, a repeated state is a loop
+        if last_event_tick.is_none_or(|last_event_tick| tick > last_event_tick) {
+            let state: Vec<Option<(u8, u8, usize)>> = guards
                 .iter()
-                .skip(per_thread as usize * thread as usize)
-                .take(per_thread as usize)
-                .take(per_thread)
-            {
-                let mut obstacles = obstacles.clone();
-                obstacles.add_obstacle(*row, *col);
-                let mut guard = guard_original.clone();
-                if get_movement_records(&map_size, &obstacles, &mut guard).looped {
-                    count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                .map(|guard| match guard.status {
+                    PatrolStatus::Patrolling => Some((
+                        guard.position.row,
+                        guard.position.col,
+                        guard.position.facing.index(),
+                    )),
+                    _ => None,
+                })
+                .collect();
+            if let Some(loop_tick) = seen_states.insert(state, tick) {
+                for guard in guards.iter_mut() {
+                    if guard.status == PatrolStatus::Patrolling {
+                        guard.status = PatrolStatus::Looped(loop_tick);
+                    }
                 }
-                obstacles.remove_obstacle(*row, *col);
             }
-        };
-        for i in 0..=thread_count {
-            scope.spawn(move || calc(i));
         }
-    });
-    count.load(std::sync::atomic::Ordering::Acquire)
+        if guards
+            .iter()
+            .all(|guard| guard.status != PatrolStatus::Patrolling)
+        {
+            break;
+        }
+        for i in 0..guards.len() {
+            if guards[i].status != PatrolStatus::Patrolling {
+                continue;
+            }
+            let (row, col) = match guards[i].position.next_position(map_size) {
+                Some(next_position) => next_position,
+                None => {
+                    guards[i].status = PatrolStatus::Left(tick);
+                    continue;
+                }
+            };
+            let is_guard_ahead = guards.iter().any(|other| {
+                other.status == PatrolStatus::Patrolling
+                    && (other.position.row, other.position.col) == (row, col)
+            });
+            let guard = &mut guards[i];
+            if obstacles.contains(row, col) || is_guard_ahead {
+                guard.position.turn_right();
+            } else {
+                guard.position.row = row;
+                guard.position.col = col;
+                guard.visited.insert((row, col));
+            }
+        }
+    }
+    guards
+}
+
+fn format_patrol(guards: &[PatrollingGuard], max_ticks: usize) -> String {
+    guards
+        .iter()
+        .enumerate()
+        .map(|(i, guard)| {
+            let status = match guard.status {
+                PatrolStatus::Patrolling => format!("still patrolling after {} ticks", max_ticks),
+                PatrolStatus::Left(tick) => format!("left the map at tick {}", tick),
+                PatrolStatus::Looped(tick) => format!("looping since tick {}", tick),
+            };
+            format!(
+                "Guard {}: visited {} positions, {}",
+                i + 1,
+                guard.visited.len(),
+                status
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn is_on_paths(paths: &HashMap<u8, Vec<Path>>, line: u8, position: u8) -> bool {
+    paths.get(&line).is_some_and(|paths| {
+        paths
+            .iter()
+            .any(|path| path.0 <= position && position <= path.1)
+    })
+}
+
+fn render_path(
+    map_size: &MapSize,
+    obstacles: &ObstacleHashMap,
+    movement_records: &MovementRecords,
+    guard_original: &GuardPosition,
+    obstructions: &[(u8, u8)],
+) -> String {
+    let obstructions: HashSet<&(u8, u8)> = obstructions.iter().collect();
+    (0..map_size.height)
+        .map(|row| {
+            (0..map_size.width)
+                .map(|col| {
+                    let is_obstacle = obstacles
+                        .rows
+                        .get(&row)
+                        .is_some_and(|obstacle_row| obstacle_row.contains(&col));
+                    let horizontal = is_on_paths(&movement_records.rows, row, col);
+                    let vertical = is_on_paths(&movement_records.cols, col, row);
+                    if is_obstacle {
+                        '#'
+                    } else if obstructions.contains(&(row, col)) {
+                        'O'
+                    } else if (row, col) == (guard_original.row, guard_original.col) {
+                        guard_original.facing.to_char()
+                    } else {
+                        match (horizontal, vertical) {
+                            (true, true) => '+',
+                            (true, false) => '-',
+                            (false, true) => '|',
+                            (false, false) => '.',
+                        }
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn paths_to_json(paths: &HashMap<u8, Vec<Path>>, line_key: &str) -> String {
+    let mut lines: Vec<&u8> = paths.keys().collect();
+    lines.sort();
+    lines
+        .into_iter()
+        .flat_map(|line| {
+            paths[line].iter().map(move |path| {
+                format!(
+                    "{{\"{}\": {}, \"from\": {}, \"to\": {}}}",
+                    line_key, line, path.0, path.1
+                )
+            })
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn to_json(
+    map_size: &MapSize,
+    movement_records: &MovementRecords,
+    guard_original: &GuardPosition,
+    obstructions: &[(u8, u8)],
+) -> String {
+    let obstructions = obstructions
+        .iter()
+        .map(|(row, col)| format!("{{\"row\": {}, \"col\": {}}}", row, col))
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!(
+        "{{\n  \"width\": {},\n  \"height\": {},\n  \"guard\": {{\"row\": {}, \"col\": {}, \"facing\": \"{}\"}},\n  \"rows\": [{}],\n  \"cols\": [{}],\n  \"obstructions\": [{}]\n}}\n",
+        map_size.width,
+        map_size.height,
+        guard_original.row,
+        guard_original.col,
+        guard_original.facing.to_char(),
+        paths_to_json(&movement_records.rows, "row"),
+        paths_to_json(&movement_records.cols, "col"),
+        obstructions
+    )
 }
 
 pub fn run(mut args: impl Iterator<Item = String>) {
@@ -359,25 +796,58 @@ pub fn run(mut args: impl Iterator<Item = String>) {
     let raw_dataset = read_input_file(&config.in_file);
     let map_size = MapSize::from_maps(&raw_dataset);
     let obstacles = ObstacleHashMap::from_maps(&raw_dataset);
+    if config.patrol {
+        let guards = GuardPosition::all_from_maps(&raw_dataset).unwrap_or_else(|err| {
+            eprintln!("Problem parsing guards: {}", err);
+            std::process::exit(1);
+        });
+        let patrol = simulate_patrol(
+            &map_size,
+            &obstacles,
+            &guards,
+            &config.events,
+            config.max_ticks,
+        );
+        println!("{}", format_patrol(&patrol, config.max_ticks));
+        return;
+    }
     let guard_original = GuardPosition::from_maps(&raw_dataset).unwrap();
     let mut guard = guard_original.clone();
     let mut movement_records = get_movement_records(&map_size, &obstacles, &mut guard);
     simplify_visited(&mut movement_records);
+    if movement_records.looped {
+        println!("The guard never leaves the map");
+    }
 
     let distinct_visit = process_first(&movement_records);
     println!("Distinct visit: {}", distinct_visit);
 
-    let mut obstacles = obstacles;
-    let can_cause_loop = process_second(
-        &map_size,
-        &movement_records,
-        &mut obstacles,
-        &guard_original,
-    );
+    let obstructions = find_loop_obstructions(&map_size, &obstacles, &guard_original);
     println!(
         "Positions for new obstacle that can cause loop: {}",
-        can_cause_loop
+        obstructions.len()
     );
+
+    if config.render {
+        println!(
+            "{}",
+            render_path(
+                &map_size,
+                &obstacles,
+                &movement_records,
+                &guard_original,
+                &obstructions
+            )
+        );
+    }
+    if let Some(json_file) = config.json_file {
+        let json = to_json(&map_size, &movement_records, &guard_original, &obstructions);
+        fs::write(&json_file, json).unwrap_or_else(|err| {
+            eprintln!("Problem writing JSON file: {}", err);
+            std::process::exit(1);
+        });
+        println!("Path written to {}", json_file);
+    }
 }
 
 #[cfg(test)]
@@ -395,14 +865,76 @@ mod tests {
         simplify_visited(&mut movement_records);
         let distinct_visit = process_first(&movement_records);
         assert_eq!(distinct_visit, 41);
-        let mut obstacles = obstacles;
-        let can_cause_loop = process_second(
-            &map_size,
-            &movement_records,
-            &mut obstacles,
-            &guard_original,
+        let obstructions = find_loop_obstructions(&map_size, &obstacles, &guard_original);
+        assert_eq!(obstructions.len(), 6);
+        assert_eq!(
+            render_path(
+                &map_size,
+                &obstacles,
+                &movement_records,
+                &guard_original,
+                &obstructions
+            ),
+            "....#.....\n\
+             ....+---+#\n\
+             ....|...|.\n\
+             ..#.|...|.\n\
+             ..+-+-+#|.\n\
+             ..|.|.|.|.\n\
+             .#+O^-+-+.\n\
+             .+----OO#.\n\
+             #O-O--+|..\n\
+             ......#O.."
+        );
+        let json = to_json(&map_size, &movement_records, &guard_original, &obstructions);
+        assert!(json.contains("\"guard\": {\"row\": 6, \"col\": 4, \"facing\": \"^\"}"));
+        assert!(json.contains("\"obstructions\": [{\"row\": 6, \"col\": 3}, "));
+    }
+
+    #[test]
+    fn test_obstruction_not_on_start() {
+        // the guard walks into a loop that never comes back to its start cell
+        let raw_dataset = ".#...\n....#\n#....\n...#.\n.^...";
+        let map_size = MapSize::from_maps(raw_dataset);
+        let obstacles = ObstacleHashMap::from_maps(raw_dataset);
+        let guard_original = GuardPosition::from_maps(raw_dataset).unwrap();
+        let obstructions = find_loop_obstructions(&map_size, &obstacles, &guard_original);
+        assert!(!obstructions.contains(&(4, 1)));
+    }
+
+    #[test]
+    fn test_patrol() {
+        let raw_dataset = read_input_file("input/day6_ex.txt");
+        let map_size = MapSize::from_maps(&raw_dataset);
+        let obstacles = ObstacleHashMap::from_maps(&raw_dataset);
+        let guards = GuardPosition::all_from_maps(&raw_dataset).unwrap();
+        let patrol = simulate_patrol(&map_size, &obstacles, &guards, &[], MAX_TICKS);
+        assert_eq!(patrol[0].visited.len(), 41);
+        assert!(matches!(patrol[0].status, PatrolStatus::Left(_)));
+
+        let events = [
+            ObstacleEvent::from_str("0:6,3", true).unwrap(),
+            ObstacleEvent::from_str("5:0,4", false).unwrap(),
+        ];
+        let patrol = simulate_patrol(&map_size, &obstacles, &guards, &events[..1], MAX_TICKS);
+        assert!(matches!(patrol[0].status, PatrolStatus::Looped(_)));
+        let patrol = simulate_patrol(&map_size, &obstacles, &guards, &events, MAX_TICKS);
+        assert_eq!(patrol[0].status, PatrolStatus::Left(6));
+        assert_eq!(patrol[0].visited.len(), 7);
+        let patrol = simulate_patrol(&map_size, &obstacles, &guards, &events[..1], 10);
+        assert_eq!(patrol[0].status, PatrolStatus::Patrolling);
+        assert!(ObstacleEvent::from_str("3:0", true).is_err());
+
+        let raw_dataset = ".....\n.>.<.\n.....";
+        let map_size = MapSize::from_maps(raw_dataset);
+        let obstacles = ObstacleHashMap::from_maps(raw_dataset);
+        let guards = GuardPosition::all_from_maps(raw_dataset).unwrap();
+        let patrol = simulate_patrol(&map_size, &obstacles, &guards, &[], MAX_TICKS);
+        assert_eq!(
+            format_patrol(&patrol, MAX_TICKS),
+            "Guard 1: visited 3 positions, left the map at tick 3\n\
+             Guard 2: visited 2 positions, left the map at tick 2"
         );
-        assert_eq!(can_cause_loop, 6);
     }
 
     #[test]
@@ -416,13 +948,7 @@ mod tests {
         simplify_visited(&mut movement_records);
         let distinct_visit = process_first(&movement_records);
         assert_eq!(distinct_visit, 5404);
-        let mut obstacles = obstacles;
-        let can_cause_loop = process_second(
-            &map_size,
-            &movement_records,
-            &mut obstacles,
-            &guard_original,
-        );
-        assert_eq!(can_cause_loop, 1984);
+        let obstructions = find_loop_obstructions(&map_size, &obstacles, &guard_original);
+        assert_eq!(obstructions.len(), 1984);
     }
 }